@@ -0,0 +1,33 @@
+use anyhow::Result;
+use bevyhub_api::prelude::*;
+use clap::ArgMatches;
+use forky::prelude::Subcommand;
+
+
+/// Create the declared indexes for each collection,
+/// for deployments that do not set `MONGODB_ENSURE_INDEXES`.
+pub struct IndexesCommand;
+
+
+impl Subcommand for IndexesCommand {
+	fn name(&self) -> &'static str { "indexes" }
+	fn about(&self) -> &'static str {
+		"Create any missing indexes declared by each document type"
+	}
+
+	fn run(&self, _args: &ArgMatches) -> Result<()> {
+		tokio::runtime::Runtime::new()?.block_on(async move {
+			let api = Services::init().await?;
+			println!("ensuring indexes with env {:?}", api.env);
+			for info in COLLECTIONS.iter() {
+				for index in (info.indexes)() {
+					println!("{}: {}", info.name, index.keys_document());
+				}
+			}
+			api.db().ensure_indexes().await?;
+			println!("done");
+			Ok::<(), anyhow::Error>(())
+		})
+	}
+}
+
@@ -0,0 +1,93 @@
+use anyhow::Result;
+use bevyhub_api::prelude::*;
+use clap::Arg;
+use clap::ArgAction;
+use clap::ArgMatches;
+use clap::Command;
+use forky::prelude::Subcommand;
+
+
+/// Poll the registry for new scene crate versions and unpack them to the db
+pub struct IngestCommand;
+
+
+impl Subcommand for IngestCommand {
+	fn name(&self) -> &'static str { "ingest" }
+	fn about(&self) -> &'static str {
+		"Ingest new versions of crates that depend on bevyhub"
+	}
+	fn append_command(&self, command: Command) -> Command {
+		command
+			.arg(
+				Arg::new("crates")
+					.help("crate names to watch, in addition to those in the db")
+					.required(false)
+					.action(ArgAction::Append),
+			)
+			.arg(
+				Arg::new("watch")
+					.help("keep running, polling every interval")
+					.action(ArgAction::SetTrue)
+					.short('w')
+					.long("watch"),
+			)
+			.arg(
+				Arg::new("concurrency")
+					.help("maximum number of crates to unpack at once")
+					.short('c')
+					.long("concurrency")
+					.value_parser(clap::value_parser!(usize)),
+			)
+			.arg(
+				Arg::new("interval")
+					.help("seconds between polls in watch mode")
+					.short('i')
+					.long("interval")
+					.value_parser(clap::value_parser!(u64)),
+			)
+			.arg(
+				Arg::new("retry-failed")
+					.help("queue failed jobs again without waiting")
+					.action(ArgAction::SetTrue)
+					.long("retry-failed"),
+			)
+	}
+
+	fn run(&self, args: &ArgMatches) -> Result<()> {
+		tokio::runtime::Runtime::new()?.block_on(async move {
+			let mut config = IngestConfig::from_env();
+			config.crates.extend(
+				args.get_many::<String>("crates").unwrap_or_default().cloned(),
+			);
+			if let Some(concurrency) = args.get_one::<usize>("concurrency") {
+				config.concurrency = *concurrency;
+			}
+			if let Some(interval) = args.get_one::<u64>("interval") {
+				config.poll_interval = std::time::Duration::from_secs(*interval);
+			}
+			if args.get_flag("retry-failed") {
+				config.retry_failed_after = std::time::Duration::ZERO;
+			}
+
+			let api = Services::init().await?;
+			println!("ingesting with env {:?}", api.env);
+			let worker = IngestWorker::new(api, config);
+
+			if args.get_flag("watch") {
+				worker.run_forever().await
+			} else {
+				let report = worker.run_once().await?;
+				println!(
+					"queued {} crates, {} succeeded and {} failed",
+					report.queued.len(),
+					report.succeeded.len(),
+					report.failed.len()
+				);
+				for crate_id in report.failed.iter() {
+					println!("failed: {}", crate_id);
+				}
+				Ok(())
+			}
+		})
+	}
+}
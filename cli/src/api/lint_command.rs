@@ -0,0 +1,95 @@
+use super::find_workspace_manifest;
+use anyhow::Result;
+use bevyhub_api::prelude::*;
+use clap::Arg;
+use clap::ArgMatches;
+use clap::Command;
+use forky::prelude::Subcommand;
+use std::fs;
+use std::path::Path;
+
+
+/// Validate the scene metadata of a local crate before publishing
+pub struct LintCommand;
+
+
+impl Subcommand for LintCommand {
+	fn name(&self) -> &'static str { "lint" }
+	fn about(&self) -> &'static str {
+		"Check the scene metadata of a crate for problems"
+	}
+	fn append_command(&self, command: Command) -> Command {
+		command.arg(
+			Arg::new("path")
+				.help("path to the crate directory")
+				.default_value("."),
+		)
+	}
+
+	fn run(&self, args: &ArgMatches) -> Result<()> {
+		let path = Path::new(args.get_one::<String>("path").unwrap())
+			.canonicalize()?;
+		let manifest = toml::from_str::<CargoManifest>(&fs::read_to_string(
+			path.join("Cargo.toml"),
+		)?)?;
+		// a workspace member shares the lockfile of the workspace root
+		let (workspace, root) = match &manifest.workspace {
+			Some(workspace) => (Some(workspace.clone()), path.clone()),
+			None => match find_workspace_manifest(&path)? {
+				Some(workspace_toml) => (
+					toml::from_str::<CargoManifest>(&fs::read_to_string(
+						&workspace_toml,
+					)?)?
+					.workspace,
+					workspace_toml.parent().unwrap().to_path_buf(),
+				),
+				None => (None, path.clone()),
+			},
+		};
+		let cargo_lock = root.join("Cargo.lock");
+		let cargo_lock = if fs::exists(&cargo_lock)? {
+			Some(toml::from_str::<CargoLock>(&fs::read_to_string(
+				cargo_lock,
+			)?)?)
+		} else {
+			None
+		};
+		let report = ManifestReport::new(
+			&manifest,
+			workspace.as_ref(),
+			cargo_lock.as_ref(),
+			&package_files(&path)?,
+		);
+		for problem in report.problems.iter() {
+			println!("{problem}");
+		}
+		if !report.is_valid() {
+			anyhow::bail!("found {} problems", report.problems.len());
+		}
+		println!("no problems found");
+		Ok(())
+	}
+}
+
+/// Files that would be included by `cargo package`
+fn package_files(path: &Path) -> Result<Vec<String>> {
+	let output = std::process::Command::new("cargo")
+		.args([
+			"package",
+			"--list",
+			"--allow-dirty",
+			"--manifest-path",
+			&path.join("Cargo.toml").to_string_lossy(),
+		])
+		.output()?;
+	if !output.status.success() {
+		anyhow::bail!(
+			"failed to list package files:\n{}",
+			String::from_utf8_lossy(&output.stderr)
+		);
+	}
+	Ok(String::from_utf8(output.stdout)?
+		.lines()
+		.map(|line| line.to_string())
+		.collect())
+}
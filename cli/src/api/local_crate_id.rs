@@ -34,6 +34,32 @@ impl LocalCrateId {
 	}
 }
 
+impl LocalCrateId {
+	/// The nearest ancestor `Cargo.toml` declaring a `[workspace]`
+	pub fn workspace_manifest(&self) -> Result<Option<PathBuf>> {
+		find_workspace_manifest(&self.path)
+	}
+}
+
+/// The nearest ancestor of the crate at `path` with a `Cargo.toml`
+/// declaring a `[workspace]`
+pub fn find_workspace_manifest(path: &Path) -> Result<Option<PathBuf>> {
+	let mut anscestor = path.parent();
+	while let Some(path) = anscestor {
+		let cargo_toml = path.join("Cargo.toml");
+		if fs::exists(&cargo_toml)? {
+			let toml = toml::from_str::<CargoManifest>(&fs::read_to_string(
+				&cargo_toml,
+			)?)?;
+			if toml.workspace.is_some() {
+				return Ok(Some(cargo_toml));
+			}
+		}
+		anscestor = path.parent();
+	}
+	Ok(None)
+}
+
 impl std::fmt::Display for LocalCrateId {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		self.crate_id.fmt(f)
@@ -1,3 +1,12 @@
+pub mod ingest_command;
+#[allow(unused_imports)]
+pub use self::ingest_command::*;
+pub mod indexes_command;
+#[allow(unused_imports)]
+pub use self::indexes_command::*;
+pub mod lint_command;
+#[allow(unused_imports)]
+pub use self::lint_command::*;
 pub mod local_crate_id;
 #[allow(unused_imports)]
 pub use self::local_crate_id::*;
@@ -7,3 +16,6 @@ pub use self::package_locally::*;
 pub mod populate_command;
 #[allow(unused_imports)]
 pub use self::populate_command::*;
+pub mod reindex_command;
+#[allow(unused_imports)]
+pub use self::reindex_command::*;
@@ -75,6 +75,18 @@ impl Subcommand for PopulateCommand {
 			// let storage_futs =
 			// 	crate_ids.iter().map(|id| api.crate_scenes(&id.crate_id));
 			// let crates = futures::future::try_join_all(storage_futs).await?;
+			// packaged tarballs dont include the workspace, store it
+			// so that inherited fields can be resolved
+			for id in crate_ids.iter() {
+				if let Some(path) = id.workspace_manifest()? {
+					let key = storage_path::unpkg_path(
+						&id.crate_id,
+						WORKSPACE_MANIFEST_FILE,
+					);
+					api.storage().put(&key, std::fs::read(path)?.into()).await?;
+				}
+			}
+
 			let mut scene_lists = Vec::new();
 			for id in crate_ids {
 				// we need to do it sequentially to avoid crate upload before scene upload race
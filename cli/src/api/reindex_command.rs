@@ -0,0 +1,86 @@
+use anyhow::Result;
+use bevyhub_api::prelude::*;
+use clap::Arg;
+use clap::ArgAction;
+use clap::ArgMatches;
+use clap::Command;
+use forky::prelude::Subcommand;
+
+
+/// Re-unpack stored crates so their documents match the current schema,
+/// without clearing the db.
+pub struct ReindexCommand;
+
+
+impl Subcommand for ReindexCommand {
+	fn name(&self) -> &'static str { "reindex" }
+	fn about(&self) -> &'static str {
+		"Re-unpack stored crates with an outdated schema version"
+	}
+	fn append_command(&self, command: Command) -> Command {
+		command
+			.arg(
+				Arg::new("all")
+					.help("reindex every stored crate, not just outdated ones")
+					.action(ArgAction::SetTrue)
+					.short('a')
+					.long("all"),
+			)
+			.arg(
+				Arg::new("in-place")
+					.help("apply schema upgrades in place, without re-unpacking")
+					.action(ArgAction::SetTrue)
+					.long("in-place"),
+			)
+			.arg(
+				Arg::new("batch-size")
+					.help("number of crates to unpack at once")
+					.short('b')
+					.long("batch-size")
+					.value_parser(clap::value_parser!(usize)),
+			)
+			.arg(
+				Arg::new("skip")
+					.help("skip this many crates, for resuming an --all run")
+					.short('s')
+					.long("skip")
+					.value_parser(clap::value_parser!(usize)),
+			)
+	}
+
+	fn run(&self, args: &ArgMatches) -> Result<()> {
+		tokio::runtime::Runtime::new()?.block_on(async move {
+			let mut runner = MigrationRunner::default();
+			if let Some(batch_size) = args.get_one::<usize>("batch-size") {
+				runner.batch_size = *batch_size;
+			}
+			if let Some(skip) = args.get_one::<usize>("skip") {
+				runner.skip = *skip;
+			}
+			let all = args.get_flag("all");
+
+			let api = Services::init().await?;
+			println!("reindexing with env {:?}", api.env);
+
+			if args.get_flag("in-place") {
+				let count = api.upgrade_stored_documents().await?;
+				println!("upgraded {} documents", count);
+				return Ok(());
+			}
+
+			let progress = api
+				.reindex(&runner, all, |progress| println!("{}", progress))
+				.await?;
+
+			for (crate_id, err) in progress.failed.iter() {
+				println!("failed: {}\n{}", crate_id, err);
+			}
+			println!(
+				"reindexed {} crates, {} failed",
+				progress.processed.saturating_sub(runner.skip),
+				progress.failed.len()
+			);
+			Ok::<(), anyhow::Error>(())
+		})
+	}
+}
@@ -13,7 +13,14 @@ impl Subcommand for Cli {
 	fn about(&self) -> &'static str { "Welcome to the Bevyhub API CLI!" }
 
 	fn subcommands(&self) -> Vec<Box<dyn Subcommand>> {
-		vec![Box::new(aws::S3Command), Box::new(api::PopulateCommand)]
+		vec![
+			Box::new(aws::S3Command),
+			Box::new(api::PopulateCommand),
+			Box::new(api::IngestCommand),
+			Box::new(api::ReindexCommand),
+			Box::new(api::IndexesCommand),
+			Box::new(api::LintCommand),
+		]
 	}
 }
 
@@ -12,6 +12,15 @@ fn main() -> anyhow::Result<()> {
 	fs::remove_dir_all(&path).ok();
 	fs::create_dir_all(&path).ok();
 	SceneDoc::export_all_to(&path)?;
+	ResolvedScene::export_all_to(&path)?;
+	SceneBundleLock::export_all_to(&path)?;
 	CrateDoc::export_all_to(&path)?;
+	CrateQuery::export_all_to(&path)?;
+	DependentsQuery::export_all_to(&path)?;
+	ManifestReport::export_all_to(&path)?;
+	IngestStatus::export_all_to(&path)?;
+	SceneFilter::export_all_to(&path)?;
+	SceneFacets::export_all_to(&path)?;
+	ChangeEvent::<SceneDoc>::export_all_to(&path)?;
 	Ok(())
 }
@@ -51,12 +51,24 @@ pub trait CargoRegistry: 'static + Send + Sync {
 	// fn get(&mut self, crate_name: &str, version: &str);
 	// fn get_latest(&mut self, crate_name: &str);
 	async fn tarball(&self, crate_id: &CrateId) -> Result<Bytes>;
+
+	/// All crate versions that directly depend on the given crate.
+	async fn reverse_dependencies(
+		&self,
+		crate_name: &str,
+	) -> Result<Vec<ReverseDependency>> {
+		anyhow::bail!(
+			"This registry does not support reverse dependencies: {}",
+			crate_name
+		)
+	}
 }
 
 #[derive(Clone)]
 pub enum CargoRegistryEnum {
 	Cached(LocalCacheRegistry),
 	CratesIo(CratesIo),
+	Fake(FakeRegistry),
 }
 
 impl CargoRegistryEnum {
@@ -77,6 +89,7 @@ impl CargoRegistryEnum {
 		match self {
 			CargoRegistryEnum::Cached(val) => val,
 			CargoRegistryEnum::CratesIo(val) => val,
+			CargoRegistryEnum::Fake(val) => val,
 		}
 	}
 }
@@ -84,7 +97,7 @@ impl CargoRegistryEnum {
 pub type CrateIndex = Vec<CrateIndexVersion>;
 
 /// Raw value from crates.io
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct CrateIndexVersion {
 	pub name: String,
 	pub yanked: bool,
@@ -95,7 +108,7 @@ pub struct CrateIndexVersion {
 }
 
 /// Raw value from crates.io
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct CrateIndexDep {
 	pub name: String,
 	pub req: String,
@@ -105,3 +118,33 @@ pub struct CrateIndexDep {
 	// pub target: Option<String>,
 	pub kind: String,
 }
+
+/// A crate version that directly depends on another crate
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReverseDependency {
+	/// The dependent crate version
+	pub crate_id: CrateId,
+	/// The version requirement of the dependency, ie `^0.1`
+	pub req: String,
+}
+
+/// Find all unyanked versions in the index that depend on `crate_name`
+pub fn reverse_dependencies_in_index(
+	index: &CrateIndex,
+	crate_name: &str,
+) -> Result<Vec<ReverseDependency>> {
+	let mut deps = Vec::new();
+	for version in index.iter().filter(|v| !v.yanked) {
+		if let Some(dep) = version.deps.iter().find(|d| d.name == crate_name)
+		{
+			deps.push(ReverseDependency {
+				crate_id: CrateId::new(
+					&version.name,
+					Version::parse(&version.vers)?,
+				),
+				req: dep.req.clone(),
+			});
+		}
+	}
+	Ok(deps)
+}
@@ -2,6 +2,9 @@ use crate::prelude::*;
 use anyhow::Result;
 use axum::body::Bytes;
 use reqwest::Client;
+use semver::Version;
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -54,6 +57,81 @@ impl CargoRegistry for CratesIo {
 		let res = res.error_for_status()?;
 		Ok(res.bytes().await?)
 	}
+
+	/// Uses the paginated crates.io reverse dependencies api,
+	/// which only includes the latest version of each dependent crate.
+	async fn reverse_dependencies(
+		&self,
+		crate_name: &str,
+	) -> Result<Vec<ReverseDependency>> {
+		let client = Client::builder().user_agent(USER_AGENT).build()?;
+		let mut deps = Vec::new();
+		for page in 1.. {
+			self.throttle.write().await.throttle().await;
+			let url = format!(
+				"https://crates.io/api/v1/crates/{}/reverse_dependencies?page={}&per_page={}",
+				crate_name, page, REVERSE_DEPENDENCIES_PER_PAGE
+			);
+			let res = client.get(url).send().await?.error_for_status()?;
+			let res = serde_json::from_str::<ReverseDependenciesResponse>(
+				&res.text().await?,
+			)?;
+
+			let versions = res
+				.versions
+				.iter()
+				.map(|v| (v.id, v))
+				.collect::<HashMap<_, _>>();
+			for dep in res.dependencies.iter() {
+				let Some(version) = versions.get(&dep.version_id) else {
+					continue;
+				};
+				if version.yanked {
+					continue;
+				}
+				deps.push(ReverseDependency {
+					crate_id: CrateId::new(
+						&version.krate,
+						Version::parse(&version.num)?,
+					),
+					req: dep.req.clone(),
+				});
+			}
+			if res.dependencies.len() < REVERSE_DEPENDENCIES_PER_PAGE
+				|| page * REVERSE_DEPENDENCIES_PER_PAGE >= res.meta.total
+			{
+				break;
+			}
+		}
+		Ok(deps)
+	}
+}
+
+const REVERSE_DEPENDENCIES_PER_PAGE: usize = 100;
+
+/// Raw value from the crates.io api
+#[derive(Debug, Deserialize)]
+struct ReverseDependenciesResponse {
+	dependencies: Vec<ReverseDependencyDep>,
+	versions: Vec<ReverseDependencyVersion>,
+	meta: ReverseDependenciesMeta,
+}
+#[derive(Debug, Deserialize)]
+struct ReverseDependencyDep {
+	version_id: u64,
+	req: String,
+}
+#[derive(Debug, Deserialize)]
+struct ReverseDependencyVersion {
+	id: u64,
+	#[serde(rename = "crate")]
+	krate: String,
+	num: String,
+	yanked: bool,
+}
+#[derive(Debug, Deserialize)]
+struct ReverseDependenciesMeta {
+	total: usize,
 }
 
 
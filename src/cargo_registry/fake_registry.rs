@@ -0,0 +1,134 @@
+use crate::prelude::*;
+use anyhow::Result;
+use axum::body::Bytes;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+/// In-memory registry for tests, does not hit the network.
+#[derive(Default, Clone)]
+pub struct FakeRegistry {
+	indexes: Arc<RwLock<HashMap<String, CrateIndex>>>,
+	tarballs: Arc<RwLock<HashMap<CrateId, Bytes>>>,
+}
+
+impl FakeRegistry {
+	pub fn new() -> Self { Self::default() }
+
+	/// Add a version to the index of a crate,
+	/// with `deps` specified as `(name, req)` pairs.
+	pub fn with_version(
+		self,
+		crate_name: &str,
+		version: &str,
+		deps: &[(&str, &str)],
+	) -> Self {
+		self.add_version(crate_name, version, deps);
+		self
+	}
+
+	/// Like [Self::with_version], for publishing
+	/// to a registry already in use.
+	pub fn add_version(
+		&self,
+		crate_name: &str,
+		version: &str,
+		deps: &[(&str, &str)],
+	) {
+		let version = CrateIndexVersion {
+			name: crate_name.into(),
+			yanked: false,
+			vers: version.into(),
+			deps: deps
+				.iter()
+				.map(|(name, req)| CrateIndexDep {
+					name: name.to_string(),
+					req: req.to_string(),
+					optional: false,
+					default_features: true,
+					kind: "normal".into(),
+				})
+				.collect(),
+			cksum: Default::default(),
+		};
+		self.indexes
+			.write()
+			.unwrap()
+			.entry(crate_name.into())
+			.or_default()
+			.push(version);
+	}
+
+	/// Mark a version added with [Self::with_version] as yanked
+	pub fn yank(self, crate_name: &str, version: &str) -> Self {
+		if let Some(index) = self.indexes.write().unwrap().get_mut(crate_name) {
+			for entry in index.iter_mut().filter(|entry| entry.vers == version)
+			{
+				entry.yanked = true;
+			}
+		}
+		self
+	}
+
+	pub fn with_tarball(self, crate_id: CrateId, tarball: Bytes) -> Self {
+		self.tarballs.write().unwrap().insert(crate_id, tarball);
+		self
+	}
+}
+
+#[async_trait::async_trait]
+impl CargoRegistry for FakeRegistry {
+	async fn crate_index(&self, crate_name: &str) -> Result<CrateIndex> {
+		self.indexes
+			.read()
+			.unwrap()
+			.get(crate_name)
+			.cloned()
+			.ok_or_else(|| anyhow::anyhow!("crate not found: {}", crate_name))
+	}
+
+	async fn tarball(&self, crate_id: &CrateId) -> Result<Bytes> {
+		self.tarballs
+			.read()
+			.unwrap()
+			.get(crate_id)
+			.cloned()
+			.ok_or_else(|| anyhow::anyhow!("tarball not found: {}", crate_id))
+	}
+
+	async fn reverse_dependencies(
+		&self,
+		crate_name: &str,
+	) -> Result<Vec<ReverseDependency>> {
+		let indexes = self.indexes.read().unwrap();
+		let mut deps = Vec::new();
+		for index in indexes.values() {
+			deps.extend(reverse_dependencies_in_index(index, crate_name)?);
+		}
+		Ok(deps)
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use sweet::*;
+
+	#[tokio::test]
+	async fn works() -> Result<()> {
+		let registry = FakeRegistry::new()
+			.with_version("foo", "0.1.0", &[("bevyhub", "^0.1")])
+			.with_version("foo", "0.2.0", &[])
+			.with_version("bar", "0.1.0", &[]);
+
+		expect(registry.versions("foo").await?.len()).to_be(2)?;
+		expect(registry.crate_index("bazz").await).to_be_err()?;
+		let deps = registry.reverse_dependencies("bevyhub").await?;
+		expect(deps.len()).to_be(1)?;
+		expect(&deps[0].crate_id.name).to_be(&"foo".to_string())?;
+
+		Ok(())
+	}
+}
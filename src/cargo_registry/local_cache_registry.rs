@@ -29,6 +29,13 @@ impl CargoRegistry for LocalCacheRegistry {
 		self.crates_io.crate_index(crate_name).await
 	}
 
+	async fn reverse_dependencies(
+		&self,
+		crate_name: &str,
+	) -> Result<Vec<ReverseDependency>> {
+		self.crates_io.reverse_dependencies(crate_name).await
+	}
+
 	async fn tarball(&self, crate_id: &CrateId) -> Result<Bytes> {
 		let dir = "target/tarball-cache";
 		let path =
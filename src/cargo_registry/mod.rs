@@ -4,6 +4,9 @@ pub use self::cargo_registry::*;
 pub mod crates_io;
 #[allow(unused_imports)]
 pub use self::crates_io::*;
+pub mod fake_registry;
+#[allow(unused_imports)]
+pub use self::fake_registry::*;
 pub mod local_cache_registry;
 #[allow(unused_imports)]
 pub use self::local_cache_registry::*;
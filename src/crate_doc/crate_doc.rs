@@ -1,8 +1,11 @@
 use crate::prelude::*;
 use anyhow::Result;
+use cargo_manifest::Dependency;
 use cargo_manifest::MaybeInherited;
 use cargo_manifest::Package;
 use cargo_manifest::StringOrBool;
+use cargo_manifest::Workspace;
+use mongodb::bson::Document;
 use semver::Version;
 use serde::Deserialize;
 use serde::Serialize;
@@ -18,16 +21,81 @@ pub struct CrateDoc {
 	pub description: Option<String>,
 	pub keywords: Vec<String>,
 	pub authors: Vec<String>,
+	pub license: Option<String>,
+	pub homepage: Option<String>,
+	pub documentation: Option<String>,
+	pub categories: Vec<String>,
+	pub rust_version: Option<String>,
+	/// The version requirement of the `bevy` dependency, ie `0.14`
+	pub bevy_version_req: Option<String>,
+	/// Epoch millis when this version was ingested
+	pub created_ms: u64,
+	/// Whether this is the latest version of the crate
+	pub is_latest: bool,
+	/// The [SchemaVersion] this document was written with
+	#[serde(default)]
+	pub schema_version: u32,
 }
 
 
 impl HasDocId for CrateDoc {
 	fn doc_id(&self) -> DocId { self._id.clone() }
+	fn from_stored(doc: Document) -> Result<Self> { upgrade_document(doc) }
+}
+
+impl NamedCollection for CrateDoc {
+	const COLLECTION_NAME: &'static str = "crates";
+	fn indexes() -> Vec<IndexDeclaration> {
+		vec![
+			IndexDeclaration::ascending(&["crate_id.name", "crate_id.version"])
+				.unique(),
+			IndexDeclaration::ascending(&["is_latest", "crate_id.name"]),
+			IndexDeclaration::ascending(&["created_ms"]),
+			IndexDeclaration::text(&["description", "keywords"]),
+		]
+	}
+}
+
+impl SchemaVersion for CrateDoc {
+	const SCHEMA_VERSION: u32 = 3;
+	fn schema_version(&self) -> u32 { self.schema_version }
+	fn upgrades() -> Vec<SchemaUpgrade> {
+		vec![
+			// 1 -> 2: `created_ms` and `is_latest` were added,
+			// the flag is corrected on the next ingest of the crate
+			SchemaUpgrade::new(1, |doc| {
+				if !doc.contains_key("created_ms") {
+					doc.insert("created_ms", 0i64);
+				}
+				if !doc.contains_key("is_latest") {
+					doc.insert("is_latest", false);
+				}
+				Ok(())
+			}),
+			// 2 -> 3: package metadata was added, only `categories` is required
+			SchemaUpgrade::new(2, |doc| {
+				if !doc.contains_key("categories") {
+					doc.insert("categories", Vec::<String>::new());
+				}
+				Ok(())
+			}),
+		]
+	}
 }
 
 impl CrateDoc {
-	pub fn from_package<T>(pkg: Package<T>) -> Result<Self> {
-		// todo!()
+	/// Extract the package, resolving `workspace = true` fields
+	/// from the workspace manifest if provided.
+	pub fn from_manifest(
+		manifest: &CargoManifest,
+		workspace: Option<&Workspace>,
+	) -> Result<Self> {
+		let Some(pkg) = manifest.package.clone() else {
+			anyhow::bail!("Cargo.toml missing package field");
+		};
+		let ws = workspace
+			.and_then(|workspace| workspace.package.clone())
+			.unwrap_or_default();
 		let Package {
 			name,
 			version,
@@ -36,10 +104,17 @@ impl CrateDoc {
 			keywords,
 			authors,
 			repository,
+			license,
+			homepage,
+			documentation,
+			categories,
+			rust_version,
 			..
 		} = pkg;
 
-		let version = unwrap_inherited(version, "0.0.1".into());
+		let Some(version) = resolve(version, ws.version) else {
+			anyhow::bail!("Cargo.toml missing package.version field");
+		};
 		let crate_id = CrateId {
 			name: name.clone(),
 			version: Version::parse(&version)?,
@@ -48,33 +123,105 @@ impl CrateDoc {
 		Ok(Self {
 			_id: crate_id.into_doc_id(),
 			crate_id,
-			readme: map_readme(readme),
-			description: map_inherited(description),
-			repository: map_inherited(repository),
-			keywords: unwrap_inherited(keywords, Vec::new()),
-			authors: unwrap_inherited(authors, Vec::new()),
+			readme: map_readme(resolve(readme, ws.readme)),
+			description: resolve(description, ws.description),
+			repository: resolve(repository, ws.repository),
+			keywords: resolve(keywords, ws.keywords).unwrap_or_default(),
+			authors: resolve(authors, ws.authors).unwrap_or_default(),
+			license: resolve(license, ws.license),
+			homepage: resolve(homepage, ws.homepage),
+			documentation: resolve(documentation, ws.documentation),
+			categories: resolve(categories, ws.categories).unwrap_or_default(),
+			rust_version: resolve(rust_version, ws.rust_version),
+			bevy_version_req: bevy_version_req(manifest, workspace),
+			created_ms: epoch_millis(),
+			is_latest: false,
+			schema_version: Self::SCHEMA_VERSION,
 		})
 	}
 	pub fn crate_id(&self) -> &CrateId { &self.crate_id }
 }
 
-fn map_readme(readme: Option<MaybeInherited<StringOrBool>>) -> String {
+fn map_readme(readme: Option<StringOrBool>) -> String {
 	match readme {
-		Some(MaybeInherited::Local(StringOrBool::String(val))) => val,
+		Some(StringOrBool::String(val)) => val,
 		_ => "README.md".into(),
 	}
 }
 
-
-fn map_inherited<T>(val: Option<MaybeInherited<T>>) -> Option<T> {
+/// The local value, or the workspace value if inherited
+fn resolve<T>(val: Option<MaybeInherited<T>>, workspace: Option<T>) -> Option<T> {
 	match val {
 		Some(MaybeInherited::Local(val)) => Some(val),
-		_ => None,
+		Some(MaybeInherited::Inherited { .. }) => workspace,
+		None => None,
 	}
 }
-fn unwrap_inherited<T>(val: Option<MaybeInherited<T>>, or_else: T) -> T {
-	match val {
-		Some(MaybeInherited::Local(val)) => val,
-		_ => or_else,
+
+/// The version requirement of the `bevy` dependency, if any
+fn bevy_version_req(
+	manifest: &CargoManifest,
+	workspace: Option<&Workspace>,
+) -> Option<String> {
+	let dep = manifest.dependencies.as_ref()?.get("bevy")?;
+	let dep = match dep {
+		Dependency::Inherited(_) => {
+			workspace?.dependencies.as_ref()?.get("bevy")?
+		}
+		dep => dep,
+	};
+	Some(dep.req().to_string())
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use sweet::*;
+
+	#[test]
+	fn resolves_workspace() -> Result<()> {
+		let workspace = toml::from_str::<CargoManifest>(
+			r#"
+			[workspace.package]
+			version = "0.1.0"
+			license = "MIT OR Apache-2.0"
+			keywords = ["bevy"]
+			rust-version = "1.80"
+
+			[workspace.dependencies]
+			bevy = { version = "0.14", default-features = false }
+			"#,
+		)?
+		.workspace;
+		let manifest = toml::from_str::<CargoManifest>(
+			r#"
+			[package]
+			name = "foo"
+			version.workspace = true
+			license.workspace = true
+			keywords.workspace = true
+			rust-version.workspace = true
+			homepage = "https://example.com"
+			categories = ["games"]
+
+			[dependencies]
+			bevy.workspace = true
+			"#,
+		)?;
+
+		let doc = CrateDoc::from_manifest(&manifest, workspace.as_ref())?;
+		expect(doc.crate_id.to_string()).to_be("foo/0.1.0".to_string())?;
+		expect(doc.license.as_deref()).to_be(Some("MIT OR Apache-2.0"))?;
+		expect(doc.keywords).to_be(vec!["bevy".to_string()])?;
+		expect(doc.rust_version.as_deref()).to_be(Some("1.80"))?;
+		expect(doc.homepage.as_deref()).to_be(Some("https://example.com"))?;
+		expect(doc.categories).to_be(vec!["games".to_string()])?;
+		expect(doc.bevy_version_req.as_deref()).to_be(Some("0.14"))?;
+
+		// inherited version without a workspace
+		expect(CrateDoc::from_manifest(&manifest, None)).to_be_err()?;
+		Ok(())
 	}
 }
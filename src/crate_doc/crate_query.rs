@@ -0,0 +1,172 @@
+use crate::prelude::*;
+use anyhow::Result;
+use mongodb::bson::doc;
+use mongodb::bson::Document;
+use serde::Deserialize;
+use serde::Serialize;
+use ts_rs::TS;
+
+/// Maximum number of crates returned by [Services::find_crates]
+pub const MAX_CRATE_LIMIT: i64 = 100;
+
+#[derive(
+	Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum CrateSort {
+	/// Alphabetical by crate name, then the most recently ingested version,
+	/// re-ingesting a version keeps its original ingest time.
+	#[default]
+	Name,
+	/// Most recently ingested first
+	Newest,
+}
+
+impl CrateSort {
+	pub fn to_document(&self) -> Document {
+		match self {
+			CrateSort::Name => doc! { "crate_id.name": 1, "created_ms": -1 },
+			CrateSort::Newest => doc! { "created_ms": -1, "crate_id.name": 1 },
+		}
+	}
+}
+
+/// Query parameters for `/crates`, all fields are optional
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrateQuery {
+	#[ts(type = "number | null")]
+	pub limit: Option<i64>,
+	#[ts(type = "number | null")]
+	pub skip: Option<u64>,
+	pub sort: CrateSort,
+	/// Crates with this keyword
+	pub keyword: Option<String>,
+	/// Crates with this author, as it appears in the manifest
+	pub author: Option<String>,
+	/// Only the latest version of each crate
+	pub latest: bool,
+}
+
+impl CrateQuery {
+	pub fn to_filter(&self) -> Document {
+		let mut filter = Document::new();
+		if let Some(keyword) = &self.keyword {
+			filter.insert("keywords", doc! { "$in": [keyword] });
+		}
+		if let Some(author) = &self.author {
+			filter.insert("authors", doc! { "$in": [author] });
+		}
+		if self.latest {
+			filter.insert("is_latest", true);
+		}
+		filter
+	}
+}
+
+impl Services {
+	/// List crates matching the query, at most [MAX_CRATE_LIMIT]
+	pub async fn find_crates(
+		&self,
+		query: &CrateQuery,
+	) -> Result<Vec<CrateDoc>> {
+		let crates = self.db().crates();
+		let mut builder = crates
+			.find()
+			.filter(query.to_filter())
+			.sort(query.sort.to_document())
+			.limit_at_most(query.limit, MAX_CRATE_LIMIT);
+		if let Some(skip) = query.skip {
+			builder = builder.skip(skip);
+		}
+		builder.send().await?.try_collect().await
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use mongodb::bson::doc;
+	use sweet::*;
+
+	fn crate_doc(
+		name: &str,
+		version: &str,
+		created_ms: i64,
+	) -> Result<CrateDoc> {
+		Ok(mongodb::bson::from_document(doc! {
+			"_id": format!("crates.io/{name}/{version}"),
+			"crate_id": { "name": name, "version": version },
+			"readme": "",
+			"repository": null,
+			"description": null,
+			"keywords": [name],
+			"authors": ["bob"],
+			"categories": [],
+			"created_ms": created_ms,
+			"is_latest": version == "0.2.0",
+		})?)
+	}
+
+	fn names(crates: Vec<CrateDoc>) -> Vec<String> {
+		crates
+			.into_iter()
+			.map(|doc| format!("{}", doc.crate_id))
+			.collect()
+	}
+
+	#[tokio::test]
+	async fn works() -> Result<()> {
+		let api = Services::test(FakeRegistry::new());
+		api.db()
+			.crates()
+			.insert_many(&vec![
+				crate_doc("foo", "0.1.0", 1)?,
+				crate_doc("foo", "0.2.0", 3)?,
+				crate_doc("bar", "0.2.0", 2)?,
+			])
+			.await?;
+
+		let find = |query: CrateQuery| {
+			let api = api.clone();
+			async move { api.find_crates(&query).await.map(names) }
+		};
+
+		expect(find(CrateQuery::default()).await?).to_be(vec![
+			"bar/0.2.0".to_string(),
+			"foo/0.2.0".into(),
+			"foo/0.1.0".into(),
+		])?;
+		expect(
+			find(CrateQuery {
+				sort: CrateSort::Newest,
+				skip: Some(1),
+				limit: Some(1),
+				..Default::default()
+			})
+			.await?,
+		)
+		.to_be(vec!["bar/0.2.0".to_string()])?;
+		expect(
+			find(CrateQuery {
+				keyword: Some("foo".into()),
+				latest: true,
+				..Default::default()
+			})
+			.await?,
+		)
+		.to_be(vec!["foo/0.2.0".to_string()])?;
+		expect(
+			find(CrateQuery {
+				author: Some("bill".into()),
+				..Default::default()
+			})
+			.await?
+			.len(),
+		)
+		.to_be(0)?;
+		Ok(())
+	}
+}
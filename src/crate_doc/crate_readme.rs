@@ -0,0 +1,163 @@
+use crate::prelude::*;
+use ammonia::UrlRelative;
+use ammonia::UrlRelativeEvaluate;
+use anyhow::Result;
+use pulldown_cmark::Options;
+use pulldown_cmark::Parser;
+use std::borrow::Cow;
+
+impl Services {
+	/// The crate README rendered to sanitized html,
+	/// cached in storage at [storage_path::readme_html_path].
+	pub async fn crate_readme_html(&self, crate_id: &CrateId) -> Result<String> {
+		let html_path = storage_path::readme_html_path(crate_id);
+		if self.storage().exists(&html_path).await? {
+			let html = self.storage().get(&html_path).await?;
+			return Ok(String::from_utf8(html.to_vec())?);
+		}
+		let crate_doc = self.crate_doc(crate_id).await?;
+		let markdown = self.get_crate_file(crate_id, &crate_doc.readme).await?;
+		let html = render_readme(
+			&String::from_utf8_lossy(&markdown),
+			crate_id,
+			&crate_doc.readme,
+		);
+		self.storage()
+			.put(&html_path, html.clone().into_bytes().into())
+			.await?;
+		Ok(html)
+	}
+}
+
+/// Render markdown to html, removing scripts and other unsafe content.
+/// Relative urls are rewritten to the unpkg route of the crate,
+/// resolved from the directory of the readme.
+pub fn render_readme(
+	markdown: &str,
+	crate_id: &CrateId,
+	readme_path: &str,
+) -> String {
+	let mut html = String::new();
+	let parser = Parser::new_ext(markdown, Options::all());
+	pulldown_cmark::html::push_html(&mut html, parser);
+
+	let urls = UnpkgUrls {
+		base: format!(
+			"/crates/{}/versions/{}/unpkg",
+			crate_id.name, crate_id.version
+		),
+		readme_dir: match readme_path.rsplit_once('/') {
+			Some((dir, _)) => dir.to_string(),
+			None => String::new(),
+		},
+	};
+	ammonia::Builder::default()
+		.url_relative(UrlRelative::Custom(Box::new(urls)))
+		.clean(&html)
+		.to_string()
+}
+
+/// Rewrites relative urls, anchors are left as is
+struct UnpkgUrls {
+	base: String,
+	readme_dir: String,
+}
+
+impl<'a> UrlRelativeEvaluate<'a> for UnpkgUrls {
+	fn evaluate<'url>(&self, url: &'url str) -> Option<Cow<'url, str>> {
+		if url.starts_with('#') {
+			return Some(Cow::Borrowed(url));
+		}
+		let path = match url.strip_prefix('/') {
+			Some(path) => path.to_string(),
+			None => join_path(&self.readme_dir, url),
+		};
+		Some(Cow::Owned(format!("{}/{}", self.base, path)))
+	}
+}
+
+/// Join a relative path to a directory, resolving `.` and `..`
+fn join_path(dir: &str, path: &str) -> String {
+	let mut parts = dir
+		.split('/')
+		.filter(|part| !part.is_empty())
+		.collect::<Vec<_>>();
+	for part in path.split('/') {
+		match part {
+			"" | "." => {}
+			".." => {
+				parts.pop();
+			}
+			part => parts.push(part),
+		}
+	}
+	parts.join("/")
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use sweet::*;
+
+	#[test]
+	fn works() -> Result<()> {
+		let crate_id = CrateId::new("foo", semver::Version::new(0, 1, 0));
+		let html = render_readme(
+			r#"# Foo
+![logo](../assets/logo.png)
+[docs](./guide.md) [home](https://example.com) [top](#foo)
+<script>alert(1)</script>"#,
+			&crate_id,
+			"docs/README.md",
+		);
+		expect(html.as_str())
+			.to_contain("src=\"/crates/foo/versions/0.1.0/unpkg/assets/logo.png\"")?;
+		expect(html.as_str())
+			.to_contain("href=\"/crates/foo/versions/0.1.0/unpkg/docs/guide.md\"")?;
+		expect(html.as_str()).to_contain("href=\"https://example.com\"")?;
+		expect(html.as_str()).to_contain("href=\"#foo\"")?;
+		expect(html.as_str()).not().to_contain("script")?;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn ignores_crate_files() -> Result<()> {
+		let api = Services::test(FakeRegistry::new());
+		let manifest = toml::from_str::<CargoManifest>(
+			r#"
+			[package]
+			name = "readme-test"
+			version = "0.1.0"
+			readme = "README.md"
+			"#,
+		)?;
+		let crate_doc = CrateDoc::from_manifest(&manifest, None)?;
+		let crate_id = &crate_doc.crate_id;
+		api.db().crates().insert(&crate_doc).await?;
+		let html_path = storage_path::readme_html_path(crate_id);
+		if api.storage().exists(&html_path).await? {
+			api.storage().delete(&html_path).await?;
+		}
+		// unpacked files, including one at the previous cache key
+		let unpkg_path = |path: &str| storage_path::unpkg_path(crate_id, path);
+		api.storage()
+			.put_many(vec![
+				(unpkg_path("README.md"), "# Hello".into()),
+				(
+					unpkg_path("README.md.bevyhub.html"),
+					"<script>alert(1)</script>".into(),
+				),
+			])
+			.await?;
+
+		let html = api.crate_readme_html(crate_id).await?;
+		expect(html.as_str()).to_contain("<h1>Hello</h1>")?;
+		expect(html.as_str()).not().to_contain("script")?;
+		// served from the cache
+		expect(api.storage().exists(&html_path).await?).to_be_true()?;
+		expect(api.crate_readme_html(crate_id).await?).to_be(html)?;
+		Ok(())
+	}
+}
@@ -0,0 +1,186 @@
+use crate::prelude::*;
+use anyhow::Result;
+use cargo_manifest::Dependency;
+use cargo_manifest::Workspace;
+use semver::VersionReq;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use ts_rs::TS;
+
+/// How the versions of crates included by a scene were chosen
+#[derive(
+	Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolutionStrategy {
+	/// Exact versions from the `Cargo.lock` published with the crate
+	#[default]
+	CargoLock,
+	/// The highest unyanked version in the registry matching the
+	/// requirement in `Cargo.toml`, for crates published without a lockfile
+	Registry,
+}
+
+/// Resolves the exact version of a crate that scenes include
+#[derive(Debug, Clone)]
+pub enum CrateResolver {
+	Lock(CargoLock),
+	/// Version requirements by package name
+	Registry(HashMap<String, VersionReq>),
+}
+
+impl CrateResolver {
+	pub fn strategy(&self) -> ResolutionStrategy {
+		match self {
+			Self::Lock(_) => ResolutionStrategy::CargoLock,
+			Self::Registry(_) => ResolutionStrategy::Registry,
+		}
+	}
+
+	pub async fn crate_id(
+		&self,
+		api: &Services,
+		crate_name: &str,
+	) -> Result<CrateId> {
+		let req = match self {
+			Self::Lock(cargo_lock) => return cargo_lock.crate_id(crate_name),
+			Self::Registry(requirements) => {
+				requirements.get(crate_name).ok_or_else(|| {
+					anyhow::anyhow!(
+						"missing dependency in Cargo.toml: {}",
+						crate_name
+					)
+				})?
+			}
+		};
+		// versions are unyanked and sorted lowest to highest
+		let version = api
+			.registry()
+			.versions(crate_name)
+			.await?
+			.into_iter()
+			.rev()
+			.find(|version| req.matches(version))
+			.ok_or_else(|| {
+				anyhow::anyhow!("no version of {} matches {}", crate_name, req)
+			})?;
+		Ok(CrateId::new(crate_name, version))
+	}
+}
+
+/// The version requirement of each dependency, dev-dependency and
+/// build-dependency, including target specific ones,
+/// keyed by package name so renamed dependencies are found.
+pub fn dependency_requirements(
+	manifest: &CargoManifest,
+	workspace: Option<&Workspace>,
+) -> Result<HashMap<String, VersionReq>> {
+	let targets = manifest
+		.target
+		.iter()
+		.flat_map(|targets| targets.values())
+		.flat_map(|target| {
+			[
+				&target.dependencies,
+				&target.dev_dependencies,
+				&target.build_dependencies,
+			]
+		});
+	let deps = [
+		&manifest.dependencies,
+		&manifest.dev_dependencies,
+		&manifest.build_dependencies,
+	]
+	.into_iter()
+	.flatten()
+	.chain(targets)
+	.flatten();
+	let mut requirements = HashMap::new();
+	for (key, dep) in deps {
+		let dep = match dep {
+			Dependency::Inherited(_) => {
+				let Some(dep) = workspace
+					.and_then(|workspace| workspace.dependencies.as_ref())
+					.and_then(|deps| deps.get(key))
+				else {
+					anyhow::bail!("missing workspace dependency: {}", key);
+				};
+				dep
+			}
+			dep => dep,
+		};
+		let name = dep.package().unwrap_or(key);
+		requirements
+			.entry(name.to_string())
+			.or_insert(VersionReq::parse(dep.req())?);
+	}
+	Ok(requirements)
+}
+
+impl Services {
+	/// Resolve with the `Cargo.lock` published with the crate,
+	/// or the registry if there isn't one.
+	pub async fn crate_resolver(
+		&self,
+		crate_id: &CrateId,
+		manifest: &CargoManifest,
+		workspace: Option<&Workspace>,
+	) -> Result<CrateResolver> {
+		self.unpack_cargo_if_needed(crate_id).await?;
+		let path = storage_path::unpkg_path(crate_id, "Cargo.lock");
+		if self.storage().exists(&path).await? {
+			Ok(CrateResolver::Lock(self.cargo_lock(crate_id).await?))
+		} else {
+			Ok(CrateResolver::Registry(dependency_requirements(
+				manifest, workspace,
+			)?))
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use sweet::*;
+
+	#[tokio::test]
+	async fn works() -> Result<()> {
+		let api = Services::test(
+			FakeRegistry::new()
+				.with_version("scenes", "0.1.0", &[])
+				.with_version("scenes", "0.1.2", &[])
+				.with_version("scenes", "0.1.3", &[])
+				.with_version("scenes", "0.2.0", &[])
+				.yank("scenes", "0.1.3"),
+		);
+		let manifest = toml::from_str::<CargoManifest>(
+			r#"
+			[package]
+			name = "foo"
+			version = "0.1.0"
+
+			[dependencies]
+			my-scenes = { package = "scenes", version = "0.1" }
+
+			[build-dependencies]
+			build-scenes = "0.2"
+
+			[target.'cfg(target_arch = "wasm32")'.dependencies]
+			wasm-scenes = "0.3"
+			"#,
+		)?;
+		let requirements = dependency_requirements(&manifest, None)?;
+		expect(requirements.contains_key("build-scenes")).to_be_true()?;
+		expect(requirements.contains_key("wasm-scenes")).to_be_true()?;
+		let resolver =
+			CrateResolver::Registry(dependency_requirements(&manifest, None)?);
+		expect(resolver.strategy()).to_be(ResolutionStrategy::Registry)?;
+		expect(resolver.crate_id(&api, "scenes").await?)
+			.to_be(CrateId::new("scenes", semver::Version::new(0, 1, 2)))?;
+		expect(resolver.crate_id(&api, "my-scenes").await).to_be_err()?;
+		Ok(())
+	}
+}
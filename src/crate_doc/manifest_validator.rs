@@ -0,0 +1,354 @@
+use crate::prelude::*;
+use anyhow::Result;
+use cargo_manifest::Workspace;
+use semver::VersionReq;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use ts_rs::TS;
+
+/// A problem found by [validate_manifest]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct ManifestProblem {
+	/// Where the problem is, ie `package.metadata.scene[1].include[0]`
+	pub location: String,
+	pub message: String,
+}
+
+impl ManifestProblem {
+	pub fn new(location: impl Into<String>, message: impl ToString) -> Self {
+		Self {
+			location: location.into(),
+			message: message.to_string(),
+		}
+	}
+}
+
+impl std::fmt::Display for ManifestProblem {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}: {}", self.location, self.message)
+	}
+}
+
+/// Result of validating a crate, valid if there are no problems
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct ManifestReport {
+	/// Missing if the manifest could not be read
+	pub crate_id: Option<CrateId>,
+	pub problems: Vec<ManifestProblem>,
+}
+
+impl ManifestReport {
+	pub fn is_valid(&self) -> bool { self.problems.is_empty() }
+
+	/// Validate a packaged `.crate` tarball, published crates are
+	/// normalized so only a root package declares a workspace.
+	pub fn from_tarball(tarball: &[u8]) -> Result<Self> {
+		let entries = tarball_files(tarball, |path| {
+			path == "Cargo.toml" || path == "Cargo.lock"
+		})?;
+		let file = |name: &str| {
+			entries
+				.iter()
+				.find(|(path, _)| path == name)
+				.and_then(|(_, bytes)| bytes.clone())
+		};
+		let Some(manifest) = file("Cargo.toml") else {
+			return Ok(Self::invalid(
+				"Cargo.toml",
+				"file missing from package",
+			));
+		};
+		let manifest = match toml_from_bytes::<CargoManifest>(&manifest) {
+			Ok(manifest) => manifest,
+			Err(err) => return Ok(Self::invalid("Cargo.toml", err)),
+		};
+		let cargo_lock = match file("Cargo.lock")
+			.map(|bytes| toml_from_bytes::<CargoLock>(&bytes))
+			.transpose()
+		{
+			Ok(cargo_lock) => cargo_lock,
+			Err(err) => return Ok(Self::invalid("Cargo.lock", err)),
+		};
+		let files = entries
+			.into_iter()
+			.map(|(path, _)| path)
+			.collect::<Vec<_>>();
+		Ok(Self::new(
+			&manifest,
+			manifest.workspace.as_ref(),
+			cargo_lock.as_ref(),
+			&files,
+		))
+	}
+
+	/// Validate a manifest, `files` are paths relative to the package root.
+	/// The `workspace` resolves `workspace = true` fields.
+	pub fn new(
+		manifest: &CargoManifest,
+		workspace: Option<&Workspace>,
+		cargo_lock: Option<&CargoLock>,
+		files: &[String],
+	) -> Self {
+		Self {
+			crate_id: CrateDoc::from_manifest(manifest, workspace)
+				.ok()
+				.map(|doc| doc.crate_id),
+			problems: validate_manifest(manifest, workspace, cargo_lock, files),
+		}
+	}
+
+	fn invalid(location: &str, message: impl ToString) -> Self {
+		Self {
+			crate_id: None,
+			problems: vec![ManifestProblem::new(location, message)],
+		}
+	}
+}
+
+/// Check the scene metadata of a manifest would unpack without errors.
+/// Scenes of other crates are only checked to be in the `Cargo.lock`,
+/// or the `Cargo.toml` dependencies if there is no lockfile.
+pub fn validate_manifest(
+	manifest: &CargoManifest,
+	workspace: Option<&Workspace>,
+	cargo_lock: Option<&CargoLock>,
+	files: &[String],
+) -> Vec<ManifestProblem> {
+	let mut problems = Vec::new();
+	let Some(package) = &manifest.package else {
+		problems.push(ManifestProblem::new("package", "missing package field"));
+		return problems;
+	};
+	if let Err(err) = CrateDoc::from_manifest(manifest, workspace) {
+		problems.push(ManifestProblem::new("package.version", err));
+	}
+	let Some(metadata) = &package.metadata else {
+		return problems;
+	};
+
+	let requirements = match dependency_requirements(manifest, workspace) {
+		Ok(requirements) => requirements,
+		Err(err) => {
+			problems.push(ManifestProblem::new("dependencies", err));
+			Default::default()
+		}
+	};
+	let refs = SceneRefs {
+		metadata,
+		cargo_lock,
+		requirements,
+		package_name: &package.name,
+	};
+	let mut names = HashSet::new();
+	for (index, scene) in metadata.scene.iter().enumerate() {
+		let location = format!("package.metadata.scene[{index}]");
+		if !names.insert(&scene.name) {
+			problems.push(ManifestProblem::new(
+				format!("{location}.name"),
+				format!("duplicate scene name: {}", scene.name),
+			));
+		}
+		match SceneFile::from_manifest(scene) {
+			Ok(file) => {
+				if let Some(path) = file.path() {
+					if !files.iter().any(|file| file == path) {
+						problems.push(ManifestProblem::new(
+							format!("{location}.path"),
+							format!("file not found in package: {path}"),
+						));
+					}
+				}
+			}
+			Err(err) => problems
+				.push(ManifestProblem::new(format!("{location}.path"), err)),
+		}
+		for (index, include) in scene.include.iter().enumerate() {
+			let location = format!("{location}.include[{index}]");
+			match include.into_crate_and_scene(&package.name) {
+				Ok((crate_name, scene_name)) => problems.extend(refs.check(
+					&location,
+					&crate_name,
+					&scene_name,
+					false,
+				)),
+				Err(err) => problems.push(ManifestProblem::new(location, err)),
+			}
+		}
+		match &scene.app {
+			None | Some(ManifestApp::Wasm { .. }) => {}
+			Some(app) => {
+				let location = format!("{location}.app");
+				match app.into_crate_and_scene(&package.name) {
+					Ok((crate_name, scene_name)) => problems.extend(
+						refs.check(&location, &crate_name, &scene_name, true),
+					),
+					Err(err) => {
+						problems.push(ManifestProblem::new(location, err))
+					}
+				}
+			}
+		}
+	}
+	problems
+}
+
+/// Checks references to scenes in this crate or another
+struct SceneRefs<'a> {
+	metadata: &'a ManifestMetadata,
+	cargo_lock: Option<&'a CargoLock>,
+	/// Used if there is no `Cargo.lock`
+	requirements: HashMap<String, VersionReq>,
+	package_name: &'a str,
+}
+
+impl SceneRefs<'_> {
+	/// `needs_app` checks that a scene in this crate has an app
+	fn check(
+		&self,
+		location: &str,
+		crate_name: &str,
+		scene_name: &str,
+		needs_app: bool,
+	) -> Option<ManifestProblem> {
+		if crate_name == self.package_name {
+			return match self.metadata.find_scene(scene_name) {
+				Ok(scene) if needs_app && scene.app.is_none() => {
+					Some(ManifestProblem::new(
+						location,
+						format!("scene has no app: {scene_name}"),
+					))
+				}
+				Ok(_) => None,
+				Err(err) => Some(ManifestProblem::new(location, err)),
+			};
+		}
+		match self.cargo_lock {
+			Some(cargo_lock) => cargo_lock
+				.crate_id(crate_name)
+				.err()
+				.map(|err| ManifestProblem::new(location, err)),
+			None if self.requirements.contains_key(crate_name) => None,
+			None => Some(ManifestProblem::new(
+				location,
+				format!("missing dependency in Cargo.toml: {crate_name}"),
+			)),
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use std::io::Read;
+	use sweet::*;
+
+	#[test]
+	fn works() -> Result<()> {
+		let manifest = toml::from_str::<CargoManifest>(
+			r#"
+			[package]
+			name = "foo"
+			version = "0.1.0"
+
+			[[package.metadata.scene]]
+			name = "base"
+
+			[[package.metadata.scene]]
+			name = "base"
+			path = "scenes/base.txt"
+
+			[[package.metadata.scene]]
+			name = "child"
+			path = "scenes/missing.ron"
+			include = ["base", "a/b/c", "other-crate/scene", "nope", "dep/scene"]
+			app = "base"
+
+			[dependencies]
+			dep = "0.1"
+			"#,
+		)?;
+		let files = vec!["Cargo.toml".to_string(), "scenes/base.json".into()];
+		let report = ManifestReport::new(&manifest, None, None, &files);
+		let locations = report
+			.problems
+			.iter()
+			.map(|problem| problem.location.as_str())
+			.collect::<Vec<_>>();
+		expect(report.crate_id.is_some()).to_be_true()?;
+		expect(locations).to_be(vec![
+			"package.metadata.scene[1].name",
+			"package.metadata.scene[1].path",
+			"package.metadata.scene[2].path",
+			"package.metadata.scene[2].include[1]",
+			"package.metadata.scene[2].include[2]",
+			"package.metadata.scene[2].include[3]",
+			"package.metadata.scene[2].app",
+		])?;
+
+		let manifest = CargoManifest::bevyhub_template();
+		expect(validate_manifest(&manifest, None, None, &[]).len())
+			.to_be_greater_than(0)?;
+		Ok(())
+	}
+
+	#[test]
+	fn resolves_workspace() -> Result<()> {
+		let workspace = toml::from_str::<CargoManifest>(
+			r#"
+			[workspace.package]
+			version = "0.1.0"
+
+			[workspace.dependencies]
+			dep = "0.1"
+			"#,
+		)?
+		.workspace;
+		let manifest = toml::from_str::<CargoManifest>(
+			r#"
+			[package]
+			name = "foo"
+			version.workspace = true
+
+			[[package.metadata.scene]]
+			name = "child"
+			path = "child.json"
+			include = ["dep/scene"]
+
+			[dependencies]
+			dep.workspace = true
+			"#,
+		)?;
+		let files = vec!["child.json".to_string()];
+		expect(validate_manifest(&manifest, None, None, &files).len())
+			.to_be(3)?;
+		let report =
+			ManifestReport::new(&manifest, workspace.as_ref(), None, &files);
+		expect(report.problems).to_be(vec![])?;
+		expect(report.crate_id.is_some()).to_be_true()?;
+		Ok(())
+	}
+
+	#[test]
+	fn tarball_limits() -> Result<()> {
+		let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+			Vec::new(),
+			flate2::Compression::best(),
+		));
+		let mut header = tar::Header::new_gnu();
+		header.set_size(MAX_UNPACKED_BYTES + 1);
+		header.set_cksum();
+		builder.append_data(
+			&mut header,
+			"foo-0.1.0/big.bin",
+			std::io::repeat(0).take(MAX_UNPACKED_BYTES + 1),
+		)?;
+		let tarball = builder.into_inner()?.finish()?;
+		let err = ManifestReport::from_tarball(&tarball).unwrap_err();
+		expect(err.downcast_ref::<TarballTooLarge>()).to_be_some()?;
+		Ok(())
+	}
+}
@@ -7,6 +7,24 @@ pub use self::crate_doc::*;
 pub mod crate_doc_api;
 #[allow(unused_imports)]
 pub use self::crate_doc_api::*;
+pub mod crate_query;
+#[allow(unused_imports)]
+pub use self::crate_query::*;
+pub mod crate_readme;
+#[allow(unused_imports)]
+pub use self::crate_readme::*;
+pub mod crate_resolver;
+#[allow(unused_imports)]
+pub use self::crate_resolver::*;
+pub mod manifest_validator;
+#[allow(unused_imports)]
+pub use self::manifest_validator::*;
+pub mod reindex;
+#[allow(unused_imports)]
+pub use self::reindex::*;
+pub mod set_latest_in_db;
+#[allow(unused_imports)]
+pub use self::set_latest_in_db::*;
 pub mod unpack_crate_to_db;
 #[allow(unused_imports)]
 pub use self::unpack_crate_to_db::*;
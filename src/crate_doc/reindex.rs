@@ -0,0 +1,89 @@
+use crate::prelude::*;
+use anyhow::Result;
+
+impl Services {
+	/// All crates with a [CrateDoc] or [SceneDoc] that has an outdated
+	/// [SchemaVersion], or every stored crate if `all` is true.
+	/// Sorted by name and version so that runs can be resumed with a skip.
+	pub async fn crates_to_reindex(&self, all: bool) -> Result<Vec<CrateId>> {
+		let (crates, scenes) = if all {
+			(
+				self.db().crates().find().send().await?.try_collect().await?,
+				self.db().scenes().find().send().await?.try_collect().await?,
+			)
+		} else {
+			(
+				MigrationRunner::outdated(&*self.db().crates()).await?,
+				MigrationRunner::outdated(&*self.db().scenes()).await?,
+			)
+		};
+		let mut crate_ids = crates
+			.into_iter()
+			.map(|doc| doc.crate_id)
+			.chain(scenes.into_iter().map(|doc| doc.scene_id.crate_id))
+			.collect::<Vec<_>>();
+		crate_ids.sort_by(|a, b| {
+			a.name.cmp(&b.name).then_with(|| a.version.cmp(&b.version))
+		});
+		crate_ids.dedup();
+		Ok(crate_ids)
+	}
+
+	/// Apply registered [SchemaVersion::upgrades] to stored documents in place,
+	/// without re-unpacking. Returns the number of documents written.
+	pub async fn upgrade_stored_documents(&self) -> Result<usize> {
+		Ok(MigrationRunner::upgrade_stored(&*self.db().crates()).await?
+			+ MigrationRunner::upgrade_stored(&*self.db().scenes()).await?)
+	}
+
+	/// Re-run [UnpackCargoManifest::unpack_crate_to_db] for each crate from
+	/// [Self::crates_to_reindex]. Reindexed documents are written with the
+	/// current schema version so an interrupted run can be resumed by running again.
+	pub async fn reindex(
+		&self,
+		runner: &MigrationRunner,
+		all: bool,
+		on_progress: impl Fn(&MigrationProgress),
+	) -> Result<MigrationProgress> {
+		let crate_ids = self.crates_to_reindex(all).await?;
+		let progress = runner
+			.run(
+				crate_ids,
+				|crate_id| async move {
+					self.unpack_crate_to_db(&crate_id).await?;
+					Ok(())
+				},
+				on_progress,
+			)
+			.await;
+		Ok(progress)
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use sweet::*;
+
+	#[tokio::test]
+	async fn works() -> Result<()> {
+		let api = Services::init().await?;
+		let crate_id = CrateId::bevyhub_template();
+		let (mut crate_doc, _) = api.unpack_crate_to_db(&crate_id).await?;
+		crate_doc.schema_version = 0;
+		api.db().crates().insert(&crate_doc).await?;
+
+		expect(api.crates_to_reindex(false).await?.contains(&crate_id))
+			.to_be_true()?;
+		let progress = api
+			.reindex(&MigrationRunner::default(), false, |_| {})
+			.await?;
+		expect(progress.failed.len()).to_be(0)?;
+		expect(api.crates_to_reindex(false).await?.contains(&crate_id))
+			.to_be_false()?;
+
+		Ok(())
+	}
+}
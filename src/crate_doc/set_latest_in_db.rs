@@ -0,0 +1,122 @@
+use crate::prelude::*;
+use mongodb::bson::doc;
+use mongodb::bson::Document;
+use semver::Version;
+
+#[extend::ext(name=SetLatestInDbExt)]
+pub impl Services {
+	/// Queue server side updates so that only the latest version of the
+	/// crate and its scenes are marked `is_latest: true`.
+	fn queue_latest(
+		&self,
+		crate_name: &str,
+		latest_version: &Version,
+		work: &mut UnitOfWork,
+	) {
+		for (filter, update) in
+			latest_updates("crate_id", crate_name, latest_version)
+		{
+			work.update_many(&*self.db().crates(), filter, update);
+		}
+		for (filter, update) in
+			latest_updates("scene_id.crate_id", crate_name, latest_version)
+		{
+			work.update_many(&*self.db().scenes(), filter, update);
+		}
+	}
+}
+
+/// Filter and update pairs for:
+/// 1. documents of other versions that are marked as latest
+/// 2. documents of the latest version that are not marked as latest
+///
+/// `crate_id_path` is the path to the [CrateId] in the document.
+fn latest_updates(
+	crate_id_path: &str,
+	crate_name: &str,
+	latest_version: &Version,
+) -> [(Document, Document); 2] {
+	let name = format!("{crate_id_path}.name");
+	let version_path = format!("{crate_id_path}.version");
+	let version = latest_version.to_string();
+	[
+		(
+			doc! {
+				&name: crate_name,
+				&version_path: { "$ne": &version },
+				"is_latest": true
+			},
+			doc! { "$set": { "is_latest": false } },
+		),
+		(
+			doc! {
+				&name: crate_name,
+				&version_path: &version,
+				"is_latest": false
+			},
+			doc! { "$set": { "is_latest": true } },
+		),
+	]
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use mongodb::bson::doc;
+	use sweet::*;
+
+	fn crate_doc(version: &str) -> Result<CrateDoc> {
+		let manifest = toml::from_str::<CargoManifest>(&format!(
+			r#"
+			[package]
+			name = "foo"
+			version = "{version}"
+			"#
+		))?;
+		CrateDoc::from_manifest(&manifest, None)
+	}
+
+	async fn set_latest(api: &Services, crate_id: &CrateId) -> Result<()> {
+		let latest_version =
+			api.registry().latest_version(&crate_id.name).await?;
+		let mut work = UnitOfWork::new();
+		api.queue_latest(&crate_id.name, &latest_version, &mut work);
+		api.db().commit(work).await
+	}
+
+	async fn latest(api: &Services) -> Result<Vec<String>> {
+		Ok(api
+			.db()
+			.crates()
+			.find()
+			.filter(doc! { "is_latest": true })
+			.send()
+			.await?
+			.try_collect()
+			.await?
+			.into_iter()
+			.map(|doc| doc.crate_id.version.to_string())
+			.collect())
+	}
+
+	#[tokio::test]
+	async fn works() -> Result<()> {
+		let registry = FakeRegistry::new().with_version("foo", "0.1.0", &[]);
+		let api = Services::test(registry.clone());
+
+		let first = crate_doc("0.1.0")?;
+		api.db().crates().insert(&first).await?;
+		set_latest(&api, &first.crate_id).await?;
+		expect(latest(&api).await?).to_be(vec!["0.1.0".to_string()])?;
+
+		// publish and ingest a new version
+		registry.add_version("foo", "0.2.0", &[]);
+		let second = crate_doc("0.2.0")?;
+		api.db().crates().insert(&second).await?;
+		set_latest(&api, &second.crate_id).await?;
+		expect(latest(&api).await?).to_be(vec!["0.2.0".to_string()])?;
+		Ok(())
+	}
+}
@@ -1,7 +1,5 @@
 use crate::prelude::*;
 use anyhow::Result;
-use cargo_manifest::MaybeInherited;
-use semver::Version;
 
 pub trait UnpackCargoManifest {
 	async fn unpack_crate_to_db(
@@ -11,32 +9,27 @@ pub trait UnpackCargoManifest {
 }
 
 impl UnpackCargoManifest for Services {
-	/// unpack the [crate_doc] and every [scene_doc] in the crate to the document db
+	/// unpack the [crate_doc] and every [scene_doc] in the crate to the document db.
+	/// All writes are committed together so a failure leaves the db unchanged.
 	async fn unpack_crate_to_db(
 		&self,
 		crate_id: &CrateId,
 	) -> Result<(CrateDoc, Vec<SceneDoc>)> {
 		let manifest = self.cargo_manifest(crate_id).await?;
 
+		let workspace = self.workspace_manifest(crate_id, &manifest).await?;
+		let mut crate_doc =
+			CrateDoc::from_manifest(&manifest, workspace.as_ref())?;
+		let crate_id = crate_doc.crate_id.clone();
 		let Some(package_toml) = &manifest.package else {
 			anyhow::bail!("Cargo.toml missing package field");
 		};
 
-		let Some(version) = &package_toml.version else {
-			anyhow::bail!("Cargo.toml missing package.version field");
-		};
-		let MaybeInherited::Local(version) = version else {
-			anyhow::bail!("Workspace manifests are not supported");
-		};
-
-		let version = Version::parse(version)?;
-		let crate_id = CrateId::new(&package_toml.name, version);
-
-		let crate_doc = CrateDoc::from_package(package_toml.clone())?;
-
-
-		let scene_docs = if let Some(scene_list) = &package_toml.metadata {
-			let cargo_lock = self.cargo_lock(&crate_id).await?;
+		let include_path = IncludePath::from_env();
+		let mut scene_docs = if let Some(scene_list) = &package_toml.metadata {
+			let resolver = self
+				.crate_resolver(&crate_id, &manifest, workspace.as_ref())
+				.await?;
 
 			let futs = scene_list
 				.scene
@@ -45,10 +38,11 @@ impl UnpackCargoManifest for Services {
 					SceneDoc::from_manifest(
 						self,
 						&crate_doc,
-						&cargo_lock,
+						&resolver,
 						&crate_id,
 						&scene_list,
 						scene,
+						&include_path,
 					)
 				})
 				.collect::<Vec<_>>();
@@ -57,9 +51,47 @@ impl UnpackCargoManifest for Services {
 		} else {
 			Default::default()
 		};
-		self.db().crates().insert(&crate_doc).await?;
-		self.db().scenes().insert_many(&scene_docs).await?;
-		self.set_latest_scenes_in_db(&crate_id).await?;
+
+		for scene in scene_docs.iter().filter(|scene| !scene.errors.is_empty())
+		{
+			tracing::warn!("{}: {}", scene.scene_id, scene.errors.join(", "));
+		}
+
+		let latest_version =
+			self.registry().latest_version(&crate_id.name).await?;
+		crate_doc.is_latest = crate_id.version == latest_version;
+		for scene in scene_docs.iter_mut() {
+			scene.is_latest = crate_id.version == latest_version;
+		}
+
+		// keep the original ingest time, used for sorting
+		if let Some(existing) =
+			self.db().crates().get(&crate_doc.doc_id()).await?
+		{
+			crate_doc.created_ms = existing.created_ms;
+		}
+
+		let mut work = UnitOfWork::new();
+		work.insert(&*self.db().crates(), &crate_doc)?;
+		work.insert_many(&*self.db().scenes(), &scene_docs)?;
+		self.queue_latest(&crate_id.name, &latest_version, &mut work);
+		let dependents = self
+			.queue_dependents(&scene_docs, &include_path, &mut work)
+			.await?;
+		self.db().commit(work).await?;
+		if !dependents.is_empty() {
+			tracing::info!(
+				"{}: updated {} dependent scenes",
+				crate_id,
+				dependents.len()
+			);
+		}
+		let changed = scene_docs
+			.iter()
+			.chain(dependents.iter())
+			.map(|scene| scene.scene_id.clone())
+			.collect::<Vec<_>>();
+		self.remove_scene_bundles(&changed).await?;
 
 		Ok((crate_doc, scene_docs))
 	}
@@ -0,0 +1,101 @@
+use crate::prelude::*;
+use semver::Version;
+use serde::Deserialize;
+use serde::Serialize;
+use ts_rs::TS;
+
+/// A crate found to depend on bevyhub, stored in the
+/// `discovered_crates` collection to record where it was found.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct DiscoveredCrate {
+	/// The unique database id in the form `crates.io/{crate_name}`
+	_id: DocId,
+	pub crate_name: String,
+	/// Sorted list of versions known to depend on bevyhub
+	pub versions: Vec<Version>,
+	/// The bevyhub version requirement of the latest discovered version
+	pub bevyhub_req: String,
+	/// Where this crate was most recently discovered
+	pub source: DiscoverySource,
+	/// Epoch timestamp
+	#[ts(type = "number")]
+	pub first_seen_ms: u64,
+	/// Epoch timestamp
+	#[ts(type = "number")]
+	pub last_seen_ms: u64,
+}
+
+impl HasDocId for DiscoveredCrate {
+	fn doc_id(&self) -> DocId { self._id.clone() }
+}
+
+impl NamedCollection for DiscoveredCrate {
+	const COLLECTION_NAME: &'static str = "discovered_crates";
+	fn indexes() -> Vec<IndexDeclaration> {
+		vec![IndexDeclaration::ascending(&["crate_name"]).unique()]
+	}
+}
+
+impl DiscoveredCrate {
+	pub fn doc_id_from_name(crate_name: &str) -> DocId {
+		DocId(format!("crates.io/{}", crate_name))
+	}
+
+	pub fn new(crate_name: impl Into<String>, source: DiscoverySource) -> Self {
+		let crate_name = crate_name.into();
+		let now = epoch_millis();
+		Self {
+			_id: Self::doc_id_from_name(&crate_name),
+			crate_name,
+			versions: Vec::new(),
+			bevyhub_req: Default::default(),
+			source,
+			first_seen_ms: now,
+			last_seen_ms: now,
+		}
+	}
+
+	/// Merge a newly discovered dependency, keeping the original `first_seen_ms`
+	pub fn add(&mut self, dep: &ReverseDependency, source: &DiscoverySource) {
+		let version = &dep.crate_id.version;
+		if !self.versions.contains(version) {
+			self.versions.push(version.clone());
+			self.versions.sort();
+		}
+		if self.versions.last() == Some(version) {
+			self.bevyhub_req = dep.req.clone();
+		}
+		self.source = source.clone();
+		self.last_seen_ms = epoch_millis();
+	}
+
+	pub fn crate_ids(&self) -> Vec<CrateId> {
+		self.versions
+			.iter()
+			.map(|version| CrateId::new(&self.crate_name, version.clone()))
+			.collect()
+	}
+}
+
+/// How a [DiscoveredCrate] was found
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(tag = "kind")]
+pub enum DiscoverySource {
+	/// The registry reverse dependencies api
+	#[serde(rename = "reverse_dependencies")]
+	ReverseDependencies,
+	/// A scan of a local checkout of the registry index
+	#[serde(rename = "index_mirror")]
+	IndexMirror { path: String },
+}
+
+impl DiscoverySource {
+	/// Uses an [DiscoverySource::IndexMirror] if the `INGEST_INDEX_MIRROR`
+	/// env var is set, otherwise [DiscoverySource::ReverseDependencies].
+	pub fn from_env() -> Self {
+		match std::env::var("INGEST_INDEX_MIRROR") {
+			Ok(path) => Self::IndexMirror { path },
+			Err(_) => Self::ReverseDependencies,
+		}
+	}
+}
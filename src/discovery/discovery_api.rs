@@ -0,0 +1,105 @@
+use crate::prelude::*;
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+impl Services {
+	/// Find all crates that depend on bevyhub and record
+	/// them in the `discovered_crates` collection.
+	pub async fn discover_crates(
+		&self,
+		source: &DiscoverySource,
+	) -> Result<Vec<DiscoveredCrate>> {
+		let deps = match source {
+			DiscoverySource::ReverseDependencies => {
+				self.registry()
+					.reverse_dependencies(BEVYHUB_CRATE_NAME)
+					.await?
+			}
+			DiscoverySource::IndexMirror { path } => {
+				scan_index_mirror(path, BEVYHUB_CRATE_NAME)?
+			}
+		};
+
+		let mut discovered = BTreeMap::<String, DiscoveredCrate>::new();
+		for dep in deps.iter() {
+			let name = &dep.crate_id.name;
+			if !discovered.contains_key(name) {
+				let existing = self
+					.db()
+					.collection::<DiscoveredCrate>()
+					.get(&DiscoveredCrate::doc_id_from_name(name))
+					.await?;
+				discovered.insert(
+					name.clone(),
+					existing.unwrap_or_else(|| {
+						DiscoveredCrate::new(name, source.clone())
+					}),
+				);
+			}
+			discovered.get_mut(name).unwrap().add(dep, source);
+		}
+
+		let discovered = discovered.into_values().collect::<Vec<_>>();
+		self.db()
+			.collection::<DiscoveredCrate>()
+			.insert_many(&discovered)
+			.await?;
+		Ok(discovered)
+	}
+
+	/// All discovered crate versions that have not yet been unpacked to the db
+	pub async fn discovery_candidates(&self) -> Result<Vec<CrateId>> {
+		let discovered = self
+			.db()
+			.collection::<DiscoveredCrate>()
+			.find()
+			.send()
+			.await?
+			.try_collect()
+			.await?;
+		let mut candidates = Vec::new();
+		for crate_id in discovered.iter().flat_map(|doc| doc.crate_ids()) {
+			if !self.db().crates().has(&crate_id.into_doc_id()).await? {
+				candidates.push(crate_id);
+			}
+		}
+		Ok(candidates)
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use sweet::*;
+
+	#[tokio::test]
+	async fn works() -> Result<()> {
+		let api = Services::test(
+			FakeRegistry::new()
+				.with_version("foo", "0.1.0", &[("bevyhub", "^0.1")])
+				.with_version("foo", "0.2.0", &[("bevyhub", "^0.2")])
+				.with_version("bar", "0.1.0", &[("bevy", "^0.15")]),
+		);
+
+		let discovered = api
+			.discover_crates(&DiscoverySource::ReverseDependencies)
+			.await?;
+		expect(discovered.len()).to_be(1)?;
+		expect(discovered[0].versions.len()).to_be(2)?;
+		expect(discovered[0].bevyhub_req.as_str()).to_be("^0.2")?;
+		expect(&discovered[0].source)
+			.to_be(&DiscoverySource::ReverseDependencies)?;
+
+		let stored = api
+			.db()
+			.collection::<DiscoveredCrate>()
+			.get(&DiscoveredCrate::doc_id_from_name("foo"))
+			.await?;
+		expect(stored).to_be_some()?;
+
+		expect(api.discovery_candidates().await?.len()).to_be(2)?;
+		Ok(())
+	}
+}
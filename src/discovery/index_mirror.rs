@@ -0,0 +1,78 @@
+use crate::prelude::*;
+use anyhow::Result;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Scan a local checkout of the registry index, ie `crates.io-index`,
+/// for all unyanked crate versions that depend on `crate_name`.
+pub fn scan_index_mirror(
+	path: impl AsRef<Path>,
+	crate_name: &str,
+) -> Result<Vec<ReverseDependency>> {
+	let mut deps = Vec::new();
+	for file in index_files(path.as_ref())? {
+		let text = std::fs::read_to_string(&file)?;
+		// cheap check to skip files that cant mention the crate,
+		// before parsing every line
+		if !text.contains(&format!("\"{}\"", crate_name)) {
+			continue;
+		}
+		let index = text
+			.lines()
+			.filter(|line| !line.is_empty())
+			.map(serde_json::from_str)
+			.collect::<Result<CrateIndex, _>>()
+			.map_err(|err| {
+				anyhow::anyhow!("invalid index file {:?}: {}", file, err)
+			})?;
+		deps.extend(reverse_dependencies_in_index(&index, crate_name)?);
+	}
+	Ok(deps)
+}
+
+/// All index files, excluding `config.json` and hidden directories like `.git`
+fn index_files(dir: &Path) -> Result<Vec<PathBuf>> {
+	let mut files = Vec::new();
+	for entry in std::fs::read_dir(dir)? {
+		let path = entry?.path();
+		let is_hidden = path
+			.file_name()
+			.map(|name| name.to_string_lossy().starts_with('.'))
+			.unwrap_or(false);
+		if is_hidden {
+			continue;
+		} else if path.is_dir() {
+			files.extend(index_files(&path)?);
+		} else if path.file_name().map(|n| n != "config.json").unwrap_or(false)
+		{
+			files.push(path);
+		}
+	}
+	Ok(files)
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use sweet::*;
+
+	#[test]
+	fn works() -> Result<()> {
+		let dir = "target/test-index-mirror";
+		std::fs::remove_dir_all(dir).ok();
+		std::fs::create_dir_all(format!("{dir}/fo/ob"))?;
+		std::fs::write(format!("{dir}/config.json"), "{}")?;
+		std::fs::write(
+			format!("{dir}/fo/ob/foobar"),
+			r#"{"name":"foobar","vers":"0.1.0","deps":[{"name":"bevyhub","req":"^0.1","optional":false,"default_features":true,"kind":"normal"}],"cksum":"","yanked":false}
+{"name":"foobar","vers":"0.2.0","deps":[],"cksum":"","yanked":false}"#,
+		)?;
+
+		let deps = scan_index_mirror(dir, "bevyhub")?;
+		expect(deps.len()).to_be(1)?;
+		expect(deps[0].crate_id.to_string().as_str()).to_be("foobar/0.1.0")?;
+		Ok(())
+	}
+}
@@ -0,0 +1,9 @@
+pub mod discovered_crate;
+#[allow(unused_imports)]
+pub use self::discovered_crate::*;
+pub mod discovery_api;
+#[allow(unused_imports)]
+pub use self::discovery_api::*;
+pub mod index_mirror;
+#[allow(unused_imports)]
+pub use self::index_mirror::*;
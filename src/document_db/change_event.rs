@@ -0,0 +1,58 @@
+use crate::prelude::*;
+use anyhow::Result;
+use futures::stream::BoxStream;
+use serde::Deserialize;
+use serde::Serialize;
+use ts_rs::TS;
+
+/// Capacity of the [MemoryCollection] change channel,
+/// slower receivers skip the oldest events.
+pub const CHANGE_CHANNEL_CAPACITY: usize = 256;
+
+/// Events from [DocumentCollection::watch]
+pub type ChangeStream<T> = BoxStream<'static, Result<ChangeEvent<T>>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+	Insert,
+	/// An update or replacement of an existing document
+	Update,
+	Remove,
+}
+
+impl ChangeKind {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			ChangeKind::Insert => "insert",
+			ChangeKind::Update => "update",
+			ChangeKind::Remove => "remove",
+		}
+	}
+}
+
+/// A write to a collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct ChangeEvent<T> {
+	pub kind: ChangeKind,
+	pub id: DocId,
+	/// The document after the change, `None` for removals
+	pub doc: Option<T>,
+}
+
+impl<T: HasDocId> ChangeEvent<T> {
+	pub fn new(kind: ChangeKind, doc: T) -> Self {
+		Self {
+			kind,
+			id: doc.doc_id(),
+			doc: Some(doc),
+		}
+	}
+	pub fn removed(id: DocId) -> Self {
+		Self {
+			kind: ChangeKind::Remove,
+			id,
+			doc: None,
+		}
+	}
+}
@@ -1,3 +1,4 @@
+use anyhow::Result;
 use mongodb::bson::doc;
 use mongodb::bson::Bson;
 use mongodb::bson::Document;
@@ -65,6 +66,13 @@ pub trait HasDocId:
 // + Into<Bson>
 {
 	fn doc_id(&self) -> DocId;
+
+	/// Deserialize a raw stored document, called by collections on read.
+	/// Types implementing [super::SchemaVersion] should override this
+	/// with [super::upgrade_document] so older documents are upgraded.
+	fn from_stored(doc: Document) -> Result<Self> {
+		Ok(mongodb::bson::from_document(doc)?)
+	}
 }
 
 
@@ -1,6 +1,7 @@
 use super::doc_id::DocId;
 use crate::prelude::*;
 use anyhow::Result;
+use mongodb::bson::Bson;
 use mongodb::bson::Document;
 
 #[async_trait::async_trait]
@@ -13,6 +14,7 @@ pub trait DocumentCollection<T: HasDocId>: 'static + Send + Sync {
 		document: Document,
 		skip: Option<u64>,
 		limit: Option<i64>,
+		sort: Option<Document>,
 	) -> Result<DocumentStream<T>>;
 	/// count number of documents that match the filter
 	async fn count(&self, document: Document) -> Result<u64>;
@@ -20,15 +22,66 @@ pub trait DocumentCollection<T: HasDocId>: 'static + Send + Sync {
 	async fn insert(&self, doc: &T) -> Result<DocId>;
 	async fn insert_many(&self, docs: &Vec<T>) -> Result<Vec<DocId>>;
 	async fn remove(&self, id: &DocId) -> Result<bool>;
+	/// Apply an update to a single document, see [apply_update] for
+	/// the supported operators. If `revision` is provided the update only
+	/// applies if it matches the stored [REVISION_FIELD], which is then incremented.
+	/// Returns false if the document is missing or the revision has changed.
+	async fn update_one(
+		&self,
+		id: &DocId,
+		update: Document,
+		revision: Option<u64>,
+	) -> Result<bool>;
+	/// Replace an existing document, with the same `revision` check as
+	/// [DocumentCollection::update_one].
+	async fn replace_one(&self, doc: &T, revision: Option<u64>) -> Result<bool>;
+	/// Apply a `$set`, `$unset` or `$inc` update to every document
+	/// matching the filter, returning the number modified.
+	async fn update_many(&self, filter: Document, update: Document)
+		-> Result<u64>;
+	/// Count matching documents grouped by the value of a field, most common first.
+	/// Each item of an array field is counted separately.
+	async fn group_count(
+		&self,
+		filter: Document,
+		field: &str,
+		limit: Option<i64>,
+	) -> Result<Vec<GroupCount>>;
+	/// Distinct values of a field in matching documents,
+	/// each item of an array field is a separate value.
+	async fn distinct(&self, filter: Document, field: &str) -> Result<Vec<Bson>>;
+	/// Apply the writes in order, returning the number of documents
+	/// modified, upserted or removed. Unchanged documents are not counted.
+	async fn bulk_write(&self, writes: Vec<BulkWrite<T>>) -> Result<u64>;
+	/// Subscribe to writes made after this call. Errors if the
+	/// backend does not support change notifications.
+	async fn watch(&self) -> Result<ChangeStream<T>>;
 	/// yep, empties an entire collection, be careful!
 	async fn clear(&self) -> Result<()>;
 }
 
+/// A single group from [DocumentCollection::group_count]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupCount {
+	pub value: Bson,
+	pub count: u64,
+}
+
+/// A single write in a [DocumentCollection::bulk_write]
+#[derive(Debug, Clone)]
+pub enum BulkWrite<T> {
+	/// Insert or replace, like [DocumentCollection::insert]
+	Upsert(T),
+	UpdateMany { filter: Document, update: Document },
+	Remove(DocId),
+}
+
 
 pub struct FindBuilder<'a, T: HasDocId> {
 	pub collection: &'a dyn DocumentCollection<T>,
 	pub skip: Option<u64>,
 	pub limit: Option<i64>,
+	pub sort: Option<Document>,
 	pub document: Document,
 }
 impl<'a, T: HasDocId> FindBuilder<'a, T> {
@@ -37,6 +90,7 @@ impl<'a, T: HasDocId> FindBuilder<'a, T> {
 			collection,
 			limit: None,
 			skip: None,
+			sort: None,
 			document: Document::new(),
 		}
 	}
@@ -48,13 +102,64 @@ impl<'a, T: HasDocId> FindBuilder<'a, T> {
 		self.limit = Some(limit);
 		self
 	}
+	/// Limit to an optional, user provided `limit`, at most `max`.
+	/// Missing, zero or negative limits use `max`,
+	/// as zero would be unlimited in mongodb.
+	pub fn limit_at_most(self, limit: Option<i64>, max: i64) -> Self {
+		let limit = limit.filter(|limit| *limit > 0).unwrap_or(max);
+		self.limit(limit.min(max))
+	}
+	/// Sort by one or more fields, `1` for ascending and `-1` for descending
+	pub fn sort(mut self, sort: Document) -> Self {
+		self.sort = Some(sort);
+		self
+	}
 	pub fn filter(mut self, filter: Document) -> Self {
 		self.document.extend(filter);
 		self
 	}
 	pub async fn send(self) -> Result<DocumentStream<T>> {
 		self.collection
-			.send_find(self.document, self.skip, self.limit)
+			.send_find(self.document, self.skip, self.limit, self.sort)
 			.await
 	}
 }
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use mongodb::bson::doc;
+	use sweet::*;
+
+	#[tokio::test]
+	async fn limit_at_most() -> Result<()> {
+		let collection = MemoryCollection::temp();
+		collection
+			.insert_many(&vec![
+				doc! {"_id":"foo"},
+				doc! {"_id":"bar"},
+				doc! {"_id":"bazz"},
+			])
+			.await?;
+		// zero would be unlimited in mongodb
+		for (limit, expected) in [
+			(None, 2),
+			(Some(1), 1),
+			(Some(5), 2),
+			(Some(0), 2),
+			(Some(-1), 2),
+		] {
+			let found = collection
+				.find()
+				.limit_at_most(limit, 2)
+				.send()
+				.await?
+				.try_collect()
+				.await?;
+			expect(found.len()).to_be(expected)?;
+		}
+		Ok(())
+	}
+}
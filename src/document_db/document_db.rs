@@ -1,16 +1,45 @@
 use crate::prelude::*;
 use anyhow::Result;
+use std::sync::Arc;
 
 /// Trait for storing and retrieving json-like documents,
 /// implemented by [MongoDb] and [MemoryDb]
 #[async_trait::async_trait]
 pub trait DocumentDb: 'static + Send + Sync {
-	fn scenes(&self) -> &dyn DocumentCollection<SceneDoc>;
-	fn crates(&self) -> &dyn DocumentCollection<CrateDoc>;
-	async fn clear(&self) -> Result<()> {
-		self.scenes().clear().await?;
-		self.crates().clear().await?;
-		Ok(())
+	/// The concrete backend, used by the generic methods in [DocumentDbExt]
+	fn backend(&self) -> DocumentDbBackend<'_>;
+	/// Apply all writes or none of them
+	async fn commit(&self, work: UnitOfWork) -> Result<()>;
+	/// Empty every collection
+	async fn clear(&self) -> Result<()>;
+	/// Create the [NamedCollection::indexes] of every document type,
+	/// existing indexes are left unchanged.
+	async fn ensure_indexes(&self) -> Result<()>;
+}
+
+pub enum DocumentDbBackend<'a> {
+	Mongo(&'a MongoDb),
+	Memory(&'a MemoryDb),
+}
+
+/// Typed collection access, these are generic so cannot be on [DocumentDb].
+pub trait DocumentDbExt {
+	/// Get the collection for a document type, creating it on first access.
+	fn collection<T: NamedCollection>(&self) -> Arc<dyn DocumentCollection<T>>;
+	fn scenes(&self) -> Arc<dyn DocumentCollection<SceneDoc>> {
+		self.collection()
+	}
+	fn crates(&self) -> Arc<dyn DocumentCollection<CrateDoc>> {
+		self.collection()
+	}
+}
+
+impl<D: DocumentDb + ?Sized> DocumentDbExt for D {
+	fn collection<T: NamedCollection>(&self) -> Arc<dyn DocumentCollection<T>> {
+		match self.backend() {
+			DocumentDbBackend::Mongo(db) => db.collection::<T>(),
+			DocumentDbBackend::Memory(db) => db.collection::<T>(),
+		}
 	}
 }
 
@@ -1,30 +1,49 @@
 use crate::prelude::*;
 use anyhow::Result;
+use futures::stream::BoxStream;
+use futures::StreamExt;
 use futures::TryStreamExt;
+use mongodb::bson::Document;
 
-#[derive(Debug)]
 pub enum DocumentStream<T> {
-	Cursor(mongodb::Cursor<T>),
+	/// Raw documents from a database cursor,
+	/// deserialized with [HasDocId::from_stored]
+	Stream(BoxStream<'static, Result<T>>),
 	Vec(Vec<T>),
 }
 
+impl<T> std::fmt::Debug for DocumentStream<T> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			DocumentStream::Stream(_) => write!(f, "DocumentStream::Stream"),
+			DocumentStream::Vec(vec) => {
+				write!(f, "DocumentStream::Vec({} items)", vec.len())
+			}
+		}
+	}
+}
+
 impl<T: HasDocId> DocumentStream<T> {
 	pub async fn try_next(&mut self) -> Result<Option<T>> {
 		match self {
-			DocumentStream::Cursor(cursor) => Ok(cursor.try_next().await?),
+			DocumentStream::Stream(stream) => stream.try_next().await,
 			DocumentStream::Vec(vec) => Ok(vec.pop()),
 		}
 	}
 	pub async fn try_collect(self) -> Result<Vec<T>> {
 		match self {
-			DocumentStream::Cursor(cursor) => Ok(cursor.try_collect().await?),
+			DocumentStream::Stream(stream) => stream.try_collect().await,
 			DocumentStream::Vec(vec) => Ok(vec),
 		}
 	}
 }
 
-impl<T> Into<DocumentStream<T>> for mongodb::Cursor<T> {
-	fn into(self) -> DocumentStream<T> { DocumentStream::Cursor(self) }
+impl<T: HasDocId> From<mongodb::Cursor<Document>> for DocumentStream<T> {
+	fn from(cursor: mongodb::Cursor<Document>) -> Self {
+		DocumentStream::Stream(
+			cursor.map(|doc| T::from_stored(doc?)).boxed(),
+		)
+	}
 }
 
 impl<T> Into<DocumentStream<T>> for Vec<T> {
@@ -0,0 +1,120 @@
+use mongodb::bson::Bson;
+use mongodb::bson::Document;
+use mongodb::options::IndexOptions;
+use mongodb::IndexModel;
+use std::time::Duration;
+
+/// How a field is indexed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndexField {
+	Ascending,
+	Descending,
+	/// Full text search, only one text index is allowed per collection
+	Text,
+}
+
+impl IndexField {
+	fn to_bson(self) -> Bson {
+		match self {
+			IndexField::Ascending => Bson::Int32(1),
+			IndexField::Descending => Bson::Int32(-1),
+			IndexField::Text => Bson::String("text".into()),
+		}
+	}
+}
+
+/// An index declared by [NamedCollection::indexes](super::NamedCollection::indexes),
+/// created by [MongoDb::ensure_indexes](super::MongoDb::ensure_indexes).
+/// Keys may be dotted paths, multiple keys create a compound index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexDeclaration {
+	pub keys: Vec<(String, IndexField)>,
+	pub unique: bool,
+	/// Documents are removed this long after the date in the first key,
+	/// which must be stored as a bson date.
+	pub expire_after: Option<Duration>,
+}
+
+impl IndexDeclaration {
+	fn new(fields: &[&str], kind: IndexField) -> Self {
+		Self {
+			keys: fields.iter().map(|field| (field.to_string(), kind)).collect(),
+			unique: false,
+			expire_after: None,
+		}
+	}
+
+	/// An ascending index on one or more fields
+	pub fn ascending(fields: &[&str]) -> Self {
+		Self::new(fields, IndexField::Ascending)
+	}
+
+	/// A text index on one or more string fields
+	pub fn text(fields: &[&str]) -> Self { Self::new(fields, IndexField::Text) }
+
+	/// Expire documents `after` the date in `field`
+	pub fn ttl(field: &str, after: Duration) -> Self {
+		let mut this = Self::new(&[field], IndexField::Ascending);
+		this.expire_after = Some(after);
+		this
+	}
+
+	/// Add another field to make a compound index
+	pub fn with_field(mut self, field: &str, kind: IndexField) -> Self {
+		self.keys.push((field.to_string(), kind));
+		self
+	}
+
+	pub fn unique(mut self) -> Self {
+		self.unique = true;
+		self
+	}
+
+	pub fn keys_document(&self) -> Document {
+		self.keys
+			.iter()
+			.map(|(field, kind)| (field.clone(), kind.to_bson()))
+			.collect()
+	}
+
+	pub fn to_index_model(&self) -> IndexModel {
+		let mut options = IndexOptions::default();
+		if self.unique {
+			options.unique = Some(true);
+		}
+		options.expire_after = self.expire_after;
+		IndexModel::builder()
+			.keys(self.keys_document())
+			.options(options)
+			.build()
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use mongodb::bson::doc;
+	use std::time::Duration;
+	use sweet::*;
+
+	#[test]
+	fn works() -> Result<()> {
+		let index = IndexDeclaration::ascending(&["crate_id.name"])
+			.with_field("created_ms", IndexField::Descending)
+			.unique();
+		expect(index.keys_document())
+			.to_be(doc! { "crate_id.name": 1, "created_ms": -1 })?;
+		let model = index.to_index_model();
+		expect(model.options.unwrap().unique).to_be(Some(true))?;
+
+		let model = IndexDeclaration::ttl("expires", Duration::from_secs(60))
+			.to_index_model();
+		expect(model.options.unwrap().expire_after)
+			.to_be(Some(Duration::from_secs(60)))?;
+		expect(IndexDeclaration::text(&["description"]).keys_document())
+			.to_be(doc! { "description": "text" })?;
+		Ok(())
+	}
+}
@@ -2,23 +2,37 @@ use super::doc_id::DocId;
 use super::document_collection::DocumentCollection;
 use crate::prelude::*;
 use anyhow::Result;
+use futures::StreamExt;
+use mongodb::bson::from_document;
 use mongodb::bson::to_document;
 use mongodb::bson::Bson;
 use mongodb::bson::Document;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+use tokio::sync::OwnedRwLockWriteGuard;
 use tokio::sync::RwLock;
 
 
+/// Directory of persisted collections, see [MemoryCollection::new]
+pub(crate) const MEMORY_DB_DIR: &str = "target/db";
+
 fn file_path(name: &str) -> PathBuf {
-	format!("target/db/{}.json", name).into()
+	format!("{}/{}.json", MEMORY_DB_DIR, name).into()
 }
 
 
-/// Any regular field or the $ne and $eq filters are handled.
+/// Any regular field or the supported operators are handled.
 fn is_handled_filter(name: &str) -> bool {
-	!name.starts_with("$") || vec!["$ne", "$eq", "$exists"].contains(&name)
+	!name.starts_with("$")
+		|| [
+			"$ne", "$eq", "$exists", "$in", "$nin", "$gt", "$gte", "$lt",
+			"$lte", "$all", "$text", "$or",
+		]
+		.contains(&name)
 }
 
 #[derive(Debug, Clone)]
@@ -26,32 +40,81 @@ pub struct MemoryCollection<T> {
 	pub map: Arc<RwLock<HashMap<DocId, T>>>,
 	pub name: String,
 	pub write_to_disk: bool,
+	/// Sends a [ChangeEvent] for each write, see [DocumentCollection::watch]
+	pub changes: broadcast::Sender<ChangeEvent<T>>,
+	/// Stored documents that failed to load, written back unchanged
+	/// until replaced or cleared, see [MemoryCollection::new]
+	unreadable: Arc<Mutex<HashMap<DocId, serde_json::Value>>>,
+	/// Fields searched by `$text` filters, see [Self::with_indexes]
+	pub text_fields: Vec<String>,
 }
 
 impl<T: HasDocId> MemoryCollection<T> {
 	/// Create a temporary collection that does not load from or write to disk.
-	pub fn temp() -> Self {
-		let mut this = Self::new("_temp");
-		this.write_to_disk = false;
-		this
+	pub fn temp() -> Self { Self::new_temp("_temp") }
+
+	/// Create a named temporary collection that does not load from or write to disk.
+	pub fn new_temp(name: impl Into<String>) -> Self {
+		Self {
+			map: Default::default(),
+			name: name.into(),
+			write_to_disk: false,
+			changes: broadcast::channel(CHANGE_CHANNEL_CAPACITY).0,
+			unreadable: Default::default(),
+			text_fields: Vec::new(),
+		}
 	}
 
+	/// Load the collection from `target/db/{name}.json` if it exists.
+	/// Documents are upgraded with [HasDocId::from_stored],
+	/// those that fail are logged and kept as is, so they are not
+	/// lost when the collection is saved.
 	pub fn new(name: impl Into<String>) -> Self {
 		let name = name.into();
-		let hashmap = if let Some(file) =
-			std::fs::read_to_string(file_path(&name)).ok()
-		{
-			serde_json::from_str(&file).expect("invalid json")
-		} else {
-			HashMap::default()
-		};
+		let mut hashmap = HashMap::default();
+		let mut unreadable = HashMap::default();
+		if let Some(file) = std::fs::read_to_string(file_path(&name)).ok() {
+			let raw: HashMap<DocId, serde_json::Value> =
+				serde_json::from_str(&file).expect("invalid json");
+			for (id, value) in raw {
+				match from_json(value.clone()) {
+					Ok(doc) => {
+						hashmap.insert(id, doc);
+					}
+					Err(err) => {
+						tracing::error!(
+							"{}: failed to load document {}: {:?}",
+							name,
+							id,
+							err
+						);
+						unreadable.insert(id, value);
+					}
+				}
+			}
+		}
 
 		Self {
 			map: Arc::new(RwLock::new(hashmap)),
 			write_to_disk: true,
 			name,
+			changes: broadcast::channel(CHANGE_CHANNEL_CAPACITY).0,
+			unreadable: Arc::new(Mutex::new(unreadable)),
+			text_fields: Vec::new(),
 		}
 	}
+
+	/// Search the fields of the text index in `$text` filters,
+	/// like mongodb. Other indexes are ignored.
+	pub fn with_indexes(mut self, indexes: &[IndexDeclaration]) -> Self {
+		self.text_fields = indexes
+			.iter()
+			.flat_map(|index| index.keys.iter())
+			.filter(|(_, kind)| *kind == IndexField::Text)
+			.map(|(field, _)| field.clone())
+			.collect();
+		self
+	}
 	/// A mock mongodb filter, this is a best effort and has many inconsistencies.
 	/// Real testing of queries should be done with a real mongodb instance.
 	pub async fn try_filter(&self, filter: &Document) -> Vec<T> {
@@ -68,6 +131,8 @@ impl<T: HasDocId> MemoryCollection<T> {
 		}
 
 		// let filter = to_bson(filter).unwrap();
+		let mut filter = filter.clone();
+		let text = filter.remove("$text");
 
 		self.map
 			.read()
@@ -75,28 +140,250 @@ impl<T: HasDocId> MemoryCollection<T> {
 			.values()
 			.filter(|doc| {
 				let doc = to_document(doc).unwrap();
-				compare_recursive(&doc, &filter)
+				text.as_ref().is_none_or(|text| {
+					text_matches(&doc, text, &self.text_fields)
+				}) && compare_recursive(&doc, &filter)
 			})
 			.cloned()
 			.collect()
 	}
 
+	/// Values of the field in each matching document, array items are flattened
+	async fn field_values(&self, filter: &Document, field: &str) -> Vec<Bson> {
+		let mut values = Vec::new();
+		for doc in self.try_filter(filter).await {
+			let Ok(doc) = to_document(&doc) else {
+				continue;
+			};
+			let Some((parent, key)) = parse_key_parts(&doc, field) else {
+				continue;
+			};
+			match parent.get(key) {
+				Some(Bson::Array(items)) => {
+					values.extend(items.iter().cloned())
+				}
+				Some(value) => values.push(value.clone()),
+				None => {}
+			}
+		}
+		values
+	}
+
+	/// Lock the collection for writing until the transaction
+	/// is dropped or notified, see [MemoryTransaction].
+	pub async fn begin(&self) -> MemoryTransaction<T> {
+		let map = self.map.clone().write_owned().await;
+		let next = map.clone();
+		MemoryTransaction {
+			collection: self.clone(),
+			map,
+			next,
+			events: Vec::new(),
+			modified: 0,
+			written: None,
+		}
+	}
+
+	/// Apply the writes to a copy of the documents,
+	/// so that a failed write leaves the collection unchanged.
+	async fn apply_writes(&self, writes: Vec<BulkWrite<T>>) -> Result<u64> {
+		let mut transaction = self.begin().await;
+		for write in writes {
+			transaction.apply(write)?;
+		}
+		transaction.save().await?;
+		let modified = transaction.modified;
+		transaction.notify();
+		Ok(modified)
+	}
+
+	/// Send events to any watchers, called after the write is saved
+	fn notify(&self, events: Vec<ChangeEvent<T>>) {
+		for event in events {
+			// errors only if there are no watchers
+			self.changes.send(event).ok();
+		}
+	}
+
 	async fn save_to_disk(&self, map: &HashMap<DocId, T>) -> Result<()> {
+		if let Some(temp) = self.write_temp(map)? {
+			std::fs::rename(temp, file_path(&self.name))?;
+		}
+		Ok(())
+	}
+
+	/// Write the documents to a file next to the collection file,
+	/// returning its path if the collection is persisted.
+	fn write_temp(&self, map: &HashMap<DocId, T>) -> Result<Option<PathBuf>> {
 		if !self.write_to_disk {
-			return Ok(());
+			return Ok(None);
 		}
-		let json = serde_json::to_string(map)?;
+		let mut json = self.unreadable.lock().unwrap().clone();
+		for (id, doc) in map.iter() {
+			json.insert(id.clone(), serde_json::to_value(doc)?);
+		}
+		let json = serde_json::to_string(&json)?;
 		let path = file_path(&self.name);
 		if let Some(parent) = path.parent() {
 			std::fs::create_dir_all(parent)?;
 		}
-		std::fs::write(path, json)?;
+		let temp = path.with_extension("json.tmp");
+		std::fs::write(&temp, json)?;
+		Ok(Some(temp))
+	}
+}
+
+/// Writes to a copy of a locked [MemoryCollection], other writers
+/// wait until it is dropped or notified. Dropping before
+/// [Self::save] discards the writes.
+pub struct MemoryTransaction<T> {
+	collection: MemoryCollection<T>,
+	map: OwnedRwLockWriteGuard<HashMap<DocId, T>>,
+	next: HashMap<DocId, T>,
+	/// Sent by [Self::notify]
+	events: Vec<ChangeEvent<T>>,
+	/// Documents inserted, changed or removed, like the mongodb
+	/// modified, upserted and deleted counts.
+	modified: u64,
+	/// Temporary file written by [Self::write]
+	written: Option<PathBuf>,
+}
+
+impl<T: HasDocId> MemoryTransaction<T> {
+	pub fn apply(&mut self, write: BulkWrite<T>) -> Result<()> {
+		match write {
+			BulkWrite::Upsert(doc) => {
+				let changed = match self.next.get(&doc.doc_id()) {
+					Some(prev) => to_document(prev)? != to_document(&doc)?,
+					None => true,
+				};
+				self.modified += changed as u64;
+				self.events.push(upsert(&mut self.next, doc));
+			}
+			BulkWrite::UpdateMany { filter, update } => {
+				for doc in update_matching(&mut self.next, &filter, &update)? {
+					self.modified += 1;
+					self.events.push(ChangeEvent::new(ChangeKind::Update, doc));
+				}
+			}
+			BulkWrite::Remove(id) => {
+				if self.next.remove(&id).is_some() {
+					self.modified += 1;
+					self.events.push(ChangeEvent::removed(id));
+				}
+			}
+		}
 		Ok(())
 	}
+
+	/// Write the copy to a temporary file, moved into place by [Self::save].
+	/// A failed write leaves the collection unchanged.
+	pub async fn write(&mut self) -> Result<()> {
+		self.written = self.collection.write_temp(&self.next)?;
+		Ok(())
+	}
+
+	/// Replace the documents with the written copy,
+	/// calling [Self::write] first if it has not been.
+	pub async fn save(&mut self) -> Result<()> {
+		if self.written.is_none() {
+			self.write().await?;
+		}
+		if let Some(temp) = self.written.take() {
+			std::fs::rename(temp, file_path(&self.collection.name))?;
+		}
+		*self.map = std::mem::take(&mut self.next);
+		Ok(())
+	}
+
+	/// Send the events of saved writes and release the lock
+	pub fn notify(self) { self.collection.notify(self.events); }
+}
+
+fn from_json<T: HasDocId>(value: serde_json::Value) -> Result<T> {
+	T::from_stored(to_document(&value)?)
 }
 
-fn compare_recursive(doc: &Document, filter: &Document) -> bool {
+/// Sort by each field in turn, missing values are ordered first like mongodb
+fn sort_documents<T: HasDocId>(
+	docs: &mut Vec<T>,
+	sort: &Document,
+) -> Result<()> {
+	let mut keyed = docs
+		.drain(..)
+		.map(|doc| Ok((to_document(&doc)?, doc)))
+		.collect::<Result<Vec<_>>>()?;
+	keyed.sort_by(|(a, _), (b, _)| {
+		for (field, direction) in sort.iter() {
+			let value = |doc| {
+				parse_key_parts(doc, field).and_then(|(doc, key)| doc.get(key))
+			};
+			let order = match (value(a), value(b)) {
+				(Some(a), Some(b)) => {
+					compare_order(a, b).unwrap_or(Ordering::Equal)
+				}
+				(a, b) => a.is_some().cmp(&b.is_some()),
+			};
+			let order = match as_number(direction) {
+				Some(direction) if direction < 0. => order.reverse(),
+				_ => order,
+			};
+			if order != Ordering::Equal {
+				return order;
+			}
+		}
+		Ordering::Equal
+	});
+	docs.extend(keyed.into_iter().map(|(_, doc)| doc));
+	Ok(())
+}
+
+/// Insert or replace a document, returning the change
+fn upsert<T: HasDocId>(map: &mut HashMap<DocId, T>, doc: T) -> ChangeEvent<T> {
+	let kind = match map.insert(doc.doc_id(), doc.clone()) {
+		Some(_) => ChangeKind::Update,
+		None => ChangeKind::Insert,
+	};
+	ChangeEvent::new(kind, doc)
+}
+
+/// Update every document matching the filter,
+/// returning those that were changed.
+fn update_matching<T: HasDocId>(
+	map: &mut HashMap<DocId, T>,
+	filter: &Document,
+	update: &Document,
+) -> Result<Vec<T>> {
+	let mut changed = Vec::new();
+	for value in map.values_mut() {
+		let doc = to_document(value)?;
+		if !compare_recursive(&doc, filter) {
+			continue;
+		}
+		let mut updated = doc.clone();
+		apply_update(&mut updated, update)?;
+		if updated != doc {
+			*value = from_document(updated)?;
+			changed.push(value.clone());
+		}
+	}
+	Ok(changed)
+}
+
+pub(crate) fn compare_recursive(doc: &Document, filter: &Document) -> bool {
 	filter.iter().all(|(key, value)| {
+		if key == "$or" {
+			return value
+				.as_array()
+				.map(|filters| {
+					filters.iter().any(|filter| {
+						filter.as_document().is_some_and(|filter| {
+							compare_recursive(doc, filter)
+						})
+					})
+				})
+				.unwrap_or(false);
+		}
 
 		let (doc, key) = match parse_key_parts(doc, key) {
 			Some(val) => val,
@@ -159,6 +446,28 @@ fn compare_mongo_filter(value: Option<&Bson>, filter: &Document) -> bool {
 		match (filter_key.as_str(), value) {
 			("$eq", Some(value)) => value == filter_value,
 			("$ne", Some(value)) => value != filter_value,
+			// like mongodb, missing fields are not equal to anything
+			("$ne", None) => true,
+			("$in", value) => in_array(value, filter_value),
+			("$nin", value) => !in_array(value, filter_value),
+			("$all", Some(Bson::Array(values))) => filter_value
+				.as_array()
+				.map(|all| all.iter().all(|item| values.contains(item)))
+				.unwrap_or(false),
+			("$gt", Some(value)) => {
+				compare_order(value, filter_value) == Some(Ordering::Greater)
+			}
+			("$gte", Some(value)) => matches!(
+				compare_order(value, filter_value),
+				Some(Ordering::Greater | Ordering::Equal)
+			),
+			("$lt", Some(value)) => {
+				compare_order(value, filter_value) == Some(Ordering::Less)
+			}
+			("$lte", Some(value)) => matches!(
+				compare_order(value, filter_value),
+				Some(Ordering::Less | Ordering::Equal)
+			),
 			("$exists", value) => {
 				if filter_value
 					.as_bool()
@@ -174,6 +483,81 @@ fn compare_mongo_filter(value: Option<&Bson>, filter: &Document) -> bool {
 	})
 }
 
+/// Like mongodb, an array value matches if any of its items are in the array
+fn in_array(value: Option<&Bson>, filter_value: &Bson) -> bool {
+	let Some(options) = filter_value.as_array() else {
+		return false;
+	};
+	match value {
+		Some(Bson::Array(values)) => {
+			values.iter().any(|value| options.contains(value))
+		}
+		Some(value) => options.contains(value),
+		None => options.contains(&Bson::Null),
+	}
+}
+
+/// Numbers of any type or strings can be ordered
+fn compare_order(value: &Bson, filter_value: &Bson) -> Option<Ordering> {
+	match (value, filter_value) {
+		(Bson::String(a), Bson::String(b)) => Some(a.cmp(b)),
+		(a, b) => match (as_number(a), as_number(b)) {
+			(Some(a), Some(b)) => a.partial_cmp(&b),
+			_ => None,
+		},
+	}
+}
+
+fn as_number(value: &Bson) -> Option<f64> {
+	match value {
+		Bson::Int32(val) => Some(*val as f64),
+		Bson::Int64(val) => Some(*val as f64),
+		Bson::Double(val) => Some(*val),
+		_ => None,
+	}
+}
+
+/// Approximates a mongodb `$text` search by matching documents where
+/// any string in the text index `fields` contains any of the search terms,
+/// ignoring case.
+fn text_matches(doc: &Document, value: &Bson, fields: &[String]) -> bool {
+	let Some(search) = value
+		.as_document()
+		.and_then(|value| value.get_str("$search").ok())
+	else {
+		return false;
+	};
+	let terms = search
+		.split_whitespace()
+		.map(|term| term.to_lowercase())
+		.collect::<Vec<_>>();
+	let mut strings = Vec::new();
+	for field in fields {
+		if let Some(value) =
+			parse_key_parts(doc, field).and_then(|(doc, key)| doc.get(key))
+		{
+			collect_strings(value, &mut strings);
+		}
+	}
+	strings.iter().any(|string| {
+		let string = string.to_lowercase();
+		terms.iter().any(|term| string.contains(term))
+	})
+}
+
+fn collect_strings<'a>(value: &'a Bson, strings: &mut Vec<&'a str>) {
+	match value {
+		Bson::String(val) => strings.push(val),
+		Bson::Document(doc) => {
+			doc.values().for_each(|val| collect_strings(val, strings))
+		}
+		Bson::Array(arr) => {
+			arr.iter().for_each(|val| collect_strings(val, strings))
+		}
+		_ => {}
+	}
+}
+
 
 #[async_trait::async_trait]
 impl<T: HasDocId> DocumentCollection<T> for MemoryCollection<T> {
@@ -200,10 +584,13 @@ impl<T: HasDocId> DocumentCollection<T> for MemoryCollection<T> {
 		document: Document,
 		skip: Option<u64>,
 		limit: Option<i64>,
+		sort: Option<Document>,
 	) -> Result<DocumentStream<T>> {
-		let values = self
-			.try_filter(&document)
-			.await
+		let mut values = self.try_filter(&document).await;
+		if let Some(sort) = sort {
+			sort_documents(&mut values, &sort)?;
+		}
+		let values = values
 			.into_iter()
 			.skip(skip.unwrap_or(0) as usize)
 			.take(limit.unwrap_or(1000) as usize)
@@ -213,20 +600,20 @@ impl<T: HasDocId> DocumentCollection<T> for MemoryCollection<T> {
 
 	async fn insert(&self, doc: &T) -> Result<DocId> {
 		let mut map = self.map.write().await;
-		map.insert(doc.doc_id(), doc.clone());
+		let event = upsert(&mut map, doc.clone());
 		self.save_to_disk(&*map).await?;
+		self.notify(vec![event]);
 		Ok(doc.doc_id())
 	}
 	async fn insert_many(&self, docs: &Vec<T>) -> Result<Vec<DocId>> {
 		let mut map = self.map.write().await;
-		let ids = docs
+		let events = docs
 			.iter()
-			.map(|doc| {
-				map.insert(doc.doc_id(), doc.clone());
-				doc.doc_id()
-			})
-			.collect();
+			.map(|doc| upsert(&mut map, doc.clone()))
+			.collect::<Vec<_>>();
 		self.save_to_disk(&*map).await?;
+		let ids = events.iter().map(|event| event.id.clone()).collect();
+		self.notify(events);
 		Ok(ids)
 	}
 
@@ -234,11 +621,143 @@ impl<T: HasDocId> DocumentCollection<T> for MemoryCollection<T> {
 		let mut map = self.map.write().await;
 		let success = map.remove(id).is_some();
 		self.save_to_disk(&*map).await?;
+		if success {
+			self.notify(vec![ChangeEvent::removed(id.clone())]);
+		}
 		Ok(success)
 	}
+	async fn update_one(
+		&self,
+		id: &DocId,
+		update: Document,
+		revision: Option<u64>,
+	) -> Result<bool> {
+		let filter = revision_filter(id, revision);
+		let update = revision_update(update, revision)?;
+		let mut map = self.map.write().await;
+		let Some(value) = map.get(id) else {
+			return Ok(false);
+		};
+		let mut doc = to_document(value)?;
+		if !compare_recursive(&doc, &filter) {
+			return Ok(false);
+		}
+		apply_update(&mut doc, &update)?;
+		let doc: T = from_document(doc)?;
+		map.insert(id.clone(), doc.clone());
+		self.save_to_disk(&*map).await?;
+		self.notify(vec![ChangeEvent::new(ChangeKind::Update, doc)]);
+		Ok(true)
+	}
+	async fn replace_one(
+		&self,
+		doc: &T,
+		revision: Option<u64>,
+	) -> Result<bool> {
+		let id = doc.doc_id();
+		let filter = revision_filter(&id, revision);
+		let mut map = self.map.write().await;
+		let Some(value) = map.get(&id) else {
+			return Ok(false);
+		};
+		if !compare_recursive(&to_document(value)?, &filter) {
+			return Ok(false);
+		}
+		let mut replacement = to_document(doc)?;
+		if let Some(revision) = revision {
+			replacement.insert(REVISION_FIELD, (revision + 1) as i64);
+		}
+		let replacement: T = from_document(replacement)?;
+		map.insert(id, replacement.clone());
+		self.save_to_disk(&*map).await?;
+		self.notify(vec![ChangeEvent::new(ChangeKind::Update, replacement)]);
+		Ok(true)
+	}
+	async fn update_many(
+		&self,
+		filter: Document,
+		update: Document,
+	) -> Result<u64> {
+		self.apply_writes(vec![BulkWrite::UpdateMany { filter, update }])
+			.await
+	}
+	async fn bulk_write(&self, writes: Vec<BulkWrite<T>>) -> Result<u64> {
+		self.apply_writes(writes).await
+	}
+	async fn group_count(
+		&self,
+		filter: Document,
+		field: &str,
+		limit: Option<i64>,
+	) -> Result<Vec<GroupCount>> {
+		let mut groups = Vec::<GroupCount>::new();
+		// like the mongo `$unwind` stage, nulls are skipped
+		for value in self
+			.field_values(&filter, field)
+			.await
+			.into_iter()
+			.filter(|value| *value != Bson::Null)
+		{
+			match groups.iter_mut().find(|group| group.value == value) {
+				Some(group) => group.count += 1,
+				None => groups.push(GroupCount { value, count: 1 }),
+			}
+		}
+		// like the mongo pipeline, ties are sorted by value
+		groups.sort_by(|a, b| {
+			b.count.cmp(&a.count).then_with(|| {
+				compare_order(&a.value, &b.value).unwrap_or(Ordering::Equal)
+			})
+		});
+		if let Some(limit) = limit {
+			groups.truncate(limit.max(0) as usize);
+		}
+		Ok(groups)
+	}
+	async fn distinct(
+		&self,
+		filter: Document,
+		field: &str,
+	) -> Result<Vec<Bson>> {
+		let mut values = Vec::new();
+		for value in self.field_values(&filter, field).await {
+			if !values.contains(&value) {
+				values.push(value);
+			}
+		}
+		Ok(values)
+	}
+	/// Lagging watchers skip the oldest events with a warning.
+	/// Clearing and restoring the collection is not notified.
+	async fn watch(&self) -> Result<ChangeStream<T>> {
+		let name = self.name.clone();
+		let receiver = self.changes.subscribe();
+		let stream = futures::stream::unfold(receiver, move |mut receiver| {
+			let name = name.clone();
+			async move {
+				loop {
+					match receiver.recv().await {
+						Ok(event) => return Some((Ok(event), receiver)),
+						Err(broadcast::error::RecvError::Lagged(skipped)) => {
+							tracing::warn!(
+								"{}: watcher skipped {} events",
+								name,
+								skipped
+							);
+						}
+						Err(broadcast::error::RecvError::Closed) => {
+							return None
+						}
+					}
+				}
+			}
+		});
+		Ok(stream.boxed())
+	}
 	async fn clear(&self) -> Result<()> {
 		let mut map = self.map.write().await;
 		map.clear();
+		self.unreadable.lock().unwrap().clear();
 		self.save_to_disk(&*map).await?;
 		Ok(())
 	}
@@ -255,6 +774,8 @@ impl<T: HasDocId> DocumentCollection<T> for MemoryCollection<T> {
 mod test {
 	use crate::prelude::*;
 	use anyhow::Result;
+	use futures::StreamExt;
+	use futures::TryStreamExt;
 	use mongodb::bson::doc;
 	use sweet::*;
 
@@ -313,7 +834,260 @@ mod test {
 		expect(collection.count(doc! {"address":{"$ne": null}}).await?)
 			.to_be(1)?;
 		expect(collection.count(doc! {"age":{"$eq": null}}).await?).to_be(1)?;
+		expect(collection.count(doc! {"height":{"$ne": 1}}).await?).to_be(1)?;
+		expect(
+			collection
+				.count(
+					doc! {"$or": [{"name": "bill"}, {"address.number": 1234}]},
+				)
+				.await?,
+		)
+		.to_be(1)?;
+		expect(
+			collection
+				.count(doc! {"$or": [{"name": "bill"}, {"age": 1}]})
+				.await?,
+		)
+		.to_be(0)?;
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn transaction() -> Result<()> {
+		let collection = MemoryCollection::temp();
+		let mut transaction = collection.begin().await;
+		transaction.apply(BulkWrite::Upsert(doc! {"_id":"foo"}))?;
+
+		// other writers wait for the transaction
+		let other = collection.clone();
+		let insert = tokio::spawn(async move {
+			other.insert(&doc! {"_id":"bar"}).await
+		});
+		tokio::task::yield_now().await;
+		expect(insert.is_finished()).to_be_false()?;
+
+		// dropping discards the writes and releases the lock
+		drop(transaction);
+		insert.await??;
+		expect(collection.has(&DocId::new("foo")).await?).to_be_false()?;
+		expect(collection.has(&DocId::new("bar")).await?).to_be_true()?;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn bulk_write() -> Result<()> {
+		let collection = MemoryCollection::temp();
+		collection
+			.insert_many(&vec![
+				doc! {"_id":"foo", "group": "a", "count": 1},
+				doc! {"_id":"bar", "group": "a", "count": 2},
+				doc! {"_id":"bazz", "group": "b", "count": 3},
+			])
+			.await?;
+
+		expect(
+			collection
+				.update_many(doc! {"group":"a"}, doc! {
+					"$inc": {"count": 10},
+					"$set": {"meta.updated": true}
+				})
+				.await?,
+		)
+		.to_be(2)?;
+		expect(collection.count(doc! {"meta.updated": true}).await?)
+			.to_be(2)?;
+		expect(collection.count(doc! {"count": 11}).await?).to_be(1)?;
+
+		let written = collection
+			.bulk_write(vec![
+				BulkWrite::Remove(DocId::new("foo")),
+				BulkWrite::UpdateMany {
+					filter: doc! {"group":"b"},
+					update: doc! {"$unset": {"count": ""}},
+				},
+				BulkWrite::Upsert(doc! {"_id":"qux"}),
+			])
+			.await?;
+		expect(written).to_be(3)?;
+		expect(collection.count(doc! {"count": {"$exists": false}}).await?)
+			.to_be(2)?;
+		// unchanged documents are not counted
+		expect(
+			collection
+				.bulk_write(vec![BulkWrite::Upsert(doc! {"_id":"qux"})])
+				.await?,
+		)
+		.to_be(0)?;
+
+		// a failed write leaves the collection unchanged
+		expect(
+			collection
+				.bulk_write(vec![
+					BulkWrite::Remove(DocId::new("bar")),
+					BulkWrite::UpdateMany {
+						filter: doc! {},
+						update: doc! {"$rename": {"group": "team"}},
+					},
+				])
+				.await,
+		)
+		.to_be_err()?;
+		expect(collection.has(&DocId::new("bar")).await?).to_be_true()?;
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn opens_lazily() -> Result<()> {
+		let db = MemoryDb::temp();
+		let job = IngestJob::new(CrateId::bevyhub_template());
+		db.collection::<IngestJob>().insert(&job).await?;
+		// the same collection is returned on each access
+		expect(db.collection::<IngestJob>().has(&job.doc_id()).await?)
+			.to_be_true()?;
+		expect(db.collection::<IngestJob>().name()).to_be("ingest_jobs")?;
+
+		db.clear().await?;
+		expect(db.collection::<IngestJob>().count(doc! {}).await?).to_be(0)?;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn watches() -> Result<()> {
+		let collection = MemoryCollection::temp();
+		collection.insert(&doc! {"_id":"foo"}).await?;
+		let changes = collection.watch().await?;
+
+		collection.insert(&doc! {"_id":"bar"}).await?;
+		collection
+			.update_many(doc! {}, doc! {"$set": {"seen": true}})
+			.await?;
+		collection.remove(&DocId::new("foo")).await?;
+
+		let events = changes.take(4).try_collect::<Vec<_>>().await?;
+		let kinds = events.iter().map(|event| event.kind).collect::<Vec<_>>();
+		expect(kinds).to_be(vec![
+			ChangeKind::Insert,
+			ChangeKind::Update,
+			ChangeKind::Update,
+			ChangeKind::Remove,
+		])?;
+		expect(&events[0].id).to_be(&DocId::new("bar"))?;
+		let updated = events[2].doc.as_ref().unwrap();
+		expect(updated.get_bool("seen")?).to_be_true()?;
+		expect(events[3].doc.clone()).to_be_none()?;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn aggregates() -> Result<()> {
+		let collection = MemoryCollection::temp();
+		collection
+			.insert_many(&vec![
+				doc! {"_id":"foo", "crate": {"name": "a"}, "tags": ["x", "y"]},
+				doc! {"_id":"bar", "crate": {"name": "a"}, "tags": ["y"]},
+				doc! {"_id":"bazz", "crate": {"name": "b"}, "tags": []},
+				doc! {"_id":"qux", "crate": {"name": null}},
+			])
+			.await?;
+
+		let groups =
+			collection.group_count(doc! {}, "crate.name", None).await?;
+		expect(&groups).to_be(&vec![
+			GroupCount {
+				value: "a".into(),
+				count: 2,
+			},
+			GroupCount {
+				value: "b".into(),
+				count: 1,
+			},
+		])?;
+		let groups = collection.group_count(doc! {}, "tags", Some(1)).await?;
+		expect(&groups).to_be(&vec![GroupCount {
+			value: "y".into(),
+			count: 2,
+		}])?;
+		expect(
+			collection
+				.group_count(doc! {"_id": "bazz"}, "tags", None)
+				.await?
+				.len(),
+		)
+		.to_be(0)?;
+
+		let mut tags = collection.distinct(doc! {}, "tags").await?;
+		tags.sort_by_key(|tag| tag.to_string());
+		expect(tags).to_be(vec!["x".into(), "y".into()])?;
+		Ok(())
+	}
+
+	/// A [SceneDoc] written before `replication_config` and `schema_version`
+	const SCENE_DOC_V0: &str = r#"{
+		"crates.io/foo/bar/0.1.0": {
+			"_id": "crates.io/foo/bar/0.1.0",
+			"scene_id": {
+				"crate_id": { "name": "foo", "version": "0.1.0" },
+				"scene_name": "bar"
+			},
+			"description": "The bar scene",
+			"created_ms": 1722470400000,
+			"thumbnail": { "kind": "text", "text": "🎨" },
+			"scene_include_tree": {
+				"file": { "kind": "json", "path": "scenes/bar.json" },
+				"scene_id": {
+					"crate_id": { "name": "foo", "version": "0.1.0" },
+					"scene_name": "bar"
+				},
+				"children": []
+			},
+			"app": null,
+			"repository": null,
+			"is_latest": true
+		}
+	}"#;
+
+	#[tokio::test]
+	async fn upgrades_old_documents() -> Result<()> {
+		let name = "test_scenes_v0";
+		std::fs::create_dir_all("target/db")?;
+		std::fs::write(format!("target/db/{name}.json"), SCENE_DOC_V0)?;
+
+		let mut collection = MemoryCollection::<SceneDoc>::new(name);
+		collection.write_to_disk = false;
+		let scene = collection
+			.get(&DocId::new("crates.io/foo/bar/0.1.0"))
+			.await?
+			.unwrap();
+		expect(scene.schema_version).to_be(SceneDoc::SCHEMA_VERSION)?;
+		expect(&scene.replication_config)
+			.to_be(&ReplicationConfig::default())?;
+		expect(&scene.scene_include_graph.load_order)
+			.to_be(&vec![scene.scene_id.clone()])?;
+		expect(collection.count(SceneDoc::outdated_filter()).await?)
+			.to_be(0)?;
+
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn keeps_unreadable_documents() -> Result<()> {
+		let name = "test_scenes_unreadable";
+		let path = format!("target/db/{name}.json");
+		std::fs::create_dir_all("target/db")?;
+		std::fs::write(&path, r#"{"bad": { "_id": "bad", "scene_id": 7 }}"#)?;
+
+		let collection = MemoryCollection::<SceneDoc>::new(name);
+		expect(collection.has(&DocId::new("bad")).await?).to_be_false()?;
+		// any write saves the collection
+		collection.remove(&DocId::new("missing")).await?;
+		let stored: serde_json::Value =
+			serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+		expect(stored["bad"]["scene_id"].as_i64()).to_be(Some(7))?;
 
+		collection.clear().await?;
+		expect(std::fs::read_to_string(&path)?.as_str()).to_be("{}")?;
 		Ok(())
 	}
 }
@@ -1,26 +1,244 @@
+use super::memory_collection::MEMORY_DB_DIR;
 use crate::prelude::*;
+use anyhow::Result;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::RwLock;
 
+/// An in memory db, each collection is persisted to
+/// `target/db/{name}.json` unless created with [MemoryDb::temp].
 #[derive(Clone)]
 pub struct MemoryDb {
-	// collections: HashMap<String, MemoryCollection<Bytes>>,
-	scenes: MemoryCollection<SceneDoc>,
-	crates: MemoryCollection<CrateDoc>,
+	/// Collections opened so far, created on first access
+	collections: Arc<RwLock<HashMap<&'static str, Arc<dyn AnyMemoryCollection>>>>,
+	write_to_disk: bool,
 }
 
 impl MemoryDb {
 	pub fn new() -> Self {
 		Self {
-			// collections: HashMap::new(),
-			scenes: MemoryCollection::new("scenes"),
-			crates: MemoryCollection::new("crates"),
+			collections: Default::default(),
+			write_to_disk: true,
 		}
 	}
+
+	/// Create a db that does not load from or write to disk.
+	pub fn temp() -> Self {
+		Self {
+			collections: Default::default(),
+			write_to_disk: false,
+		}
+	}
+
+	pub fn collection<T: NamedCollection>(
+		&self,
+	) -> Arc<dyn DocumentCollection<T>> {
+		let name = T::COLLECTION_NAME;
+		if let Some(collection) = self.collections.read().unwrap().get(name) {
+			return downcast::<T>(collection.as_ref());
+		}
+		let mut collections = self.collections.write().unwrap();
+		let collection = collections.entry(name).or_insert_with(|| {
+			let collection = if self.write_to_disk {
+				MemoryCollection::<T>::new(name)
+			} else {
+				MemoryCollection::<T>::new_temp(name)
+			};
+			Arc::new(collection.with_indexes(&T::indexes()))
+		});
+		downcast::<T>(collection.as_ref())
+	}
+
+	fn opened(&self) -> Vec<Arc<dyn AnyMemoryCollection>> {
+		self.collections.read().unwrap().values().cloned().collect()
+	}
+
+	fn get_opened(&self, name: &str) -> Result<Arc<dyn AnyMemoryCollection>> {
+		self.collections
+			.read()
+			.unwrap()
+			.get(name)
+			.cloned()
+			.ok_or_else(|| anyhow::anyhow!("collection not opened: {}", name))
+	}
+}
+
+fn downcast<T: NamedCollection>(
+	collection: &dyn AnyMemoryCollection,
+) -> Arc<dyn DocumentCollection<T>> {
+	let collection = collection
+		.as_any()
+		.downcast_ref::<MemoryCollection<T>>()
+		.unwrap_or_else(|| {
+			panic!(
+				"collection {} was opened with a different type",
+				T::COLLECTION_NAME
+			)
+		});
+	Arc::new(collection.clone())
+}
+
+/// A type erased [MemoryCollection], so that collections of
+/// different types can be stored and committed together.
+#[async_trait::async_trait]
+trait AnyMemoryCollection: 'static + Send + Sync {
+	fn as_any(&self) -> &dyn Any;
+	async fn begin(&self) -> Box<dyn AnyMemoryTransaction>;
+	async fn clear(&self) -> Result<()>;
+}
+
+#[async_trait::async_trait]
+impl<T: HasDocId> AnyMemoryCollection for MemoryCollection<T> {
+	fn as_any(&self) -> &dyn Any { self }
+	async fn begin(&self) -> Box<dyn AnyMemoryTransaction> {
+		Box::new(MemoryCollection::begin(self).await)
+	}
+	async fn clear(&self) -> Result<()> {
+		DocumentCollection::clear(self).await
+	}
+}
+
+/// A type erased [MemoryTransaction]
+#[async_trait::async_trait]
+trait AnyMemoryTransaction: Send + Sync {
+	fn apply(&mut self, op: &WriteOp) -> Result<()>;
+	async fn write(&mut self) -> Result<()>;
+	async fn save(&mut self) -> Result<()>;
+	fn notify(self: Box<Self>);
+}
+
+#[async_trait::async_trait]
+impl<T: HasDocId> AnyMemoryTransaction for MemoryTransaction<T> {
+	fn apply(&mut self, op: &WriteOp) -> Result<()> {
+		MemoryTransaction::apply(self, op.to_bulk_write()?)
+	}
+	async fn write(&mut self) -> Result<()> {
+		MemoryTransaction::write(self).await
+	}
+	async fn save(&mut self) -> Result<()> {
+		MemoryTransaction::save(self).await
+	}
+	fn notify(self: Box<Self>) { MemoryTransaction::notify(*self) }
 }
 
+#[async_trait::async_trait]
 impl DocumentDb for MemoryDb {
-	fn scenes(&self) -> &dyn DocumentCollection<SceneDoc> { &self.scenes }
-	fn crates(&self) -> &dyn DocumentCollection<CrateDoc> { &self.crates }
+	fn backend(&self) -> DocumentDbBackend<'_> {
+		DocumentDbBackend::Memory(self)
+	}
+
+	/// Locks the affected collections for the whole commit, in name order
+	/// so that concurrent commits cannot deadlock. Writes are applied to
+	/// copies, which are discarded if any write fails, see [save_all].
+	async fn commit(&self, work: UnitOfWork) -> Result<()> {
+		let mut names =
+			work.ops.iter().map(|op| op.collection()).collect::<Vec<_>>();
+		names.sort();
+		names.dedup();
+		let mut transactions = HashMap::new();
+		for name in names {
+			transactions.insert(name, self.get_opened(name)?.begin().await);
+		}
+
+		for op in work.ops.iter() {
+			transactions
+				.get_mut(op.collection())
+				.expect("collection is locked")
+				.apply(op)?;
+		}
+		let mut transactions = transactions.into_values().collect::<Vec<_>>();
+		save_all(&mut transactions).await?;
+		for transaction in transactions {
+			transaction.notify();
+		}
+		Ok(())
+	}
+
+	/// Memory collections are not indexed
+	async fn ensure_indexes(&self) -> Result<()> { Ok(()) }
+
+	/// Empties every opened collection, and removes those persisted
+	/// to disk but not yet opened.
+	async fn clear(&self) -> Result<()> {
+		for collection in self.opened() {
+			collection.clear().await?;
+		}
+		if !self.write_to_disk {
+			return Ok(());
+		}
+		let Ok(entries) = std::fs::read_dir(MEMORY_DB_DIR) else {
+			return Ok(());
+		};
+		for entry in entries {
+			let path = entry?.path();
+			let Some(name) = path
+				.extension()
+				.filter(|ext| *ext == "json")
+				.and_then(|_| path.file_stem())
+				.and_then(|name| name.to_str())
+			else {
+				continue;
+			};
+			if !self.collections.read().unwrap().contains_key(name) {
+				std::fs::remove_file(&path)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+
+/// Write every collection to disk before replacing the documents
+/// of any, so a failed write leaves them all unchanged.
+async fn save_all(
+	transactions: &mut [Box<dyn AnyMemoryTransaction>],
+) -> Result<()> {
+	for transaction in transactions.iter_mut() {
+		transaction.write().await?;
+	}
+	for transaction in transactions.iter_mut() {
+		transaction.save().await?;
+	}
+	Ok(())
 }
 
 
-// See memory_collection.rs for tests
+// See memory_collection.rs for more tests
+#[cfg(test)]
+mod test {
+	use super::save_all;
+	use super::AnyMemoryTransaction;
+	use crate::prelude::*;
+	use anyhow::Result;
+	use mongodb::bson::doc;
+	use mongodb::bson::Document;
+	use sweet::*;
+
+	#[tokio::test]
+	async fn failed_save() -> Result<()> {
+		let first = MemoryCollection::<Document>::new("test_save_first");
+		let second = MemoryCollection::<Document>::new("test_save_second");
+		first.clear().await?;
+		second.clear().await?;
+		// a directory in place of the temporary file fails the write
+		let blocked = "target/db/test_save_second.json.tmp";
+		std::fs::create_dir_all(blocked)?;
+
+		let mut transactions: Vec<Box<dyn AnyMemoryTransaction>> = Vec::new();
+		for collection in [&first, &second] {
+			let mut transaction = collection.begin().await;
+			transaction.apply(BulkWrite::Upsert(doc! {"_id":"foo"}))?;
+			transactions.push(Box::new(transaction));
+		}
+		let result = save_all(&mut transactions).await;
+		std::fs::remove_dir(blocked)?;
+		expect(result).to_be_err()?;
+		drop(transactions);
+
+		expect(first.has(&DocId::new("foo")).await?).to_be_false()?;
+		let saved = std::fs::read_to_string("target/db/test_save_first.json")?;
+		expect(saved.as_str()).to_be("{}")?;
+		Ok(())
+	}
+}
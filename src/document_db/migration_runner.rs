@@ -0,0 +1,152 @@
+use crate::prelude::*;
+use anyhow::Result;
+use std::fmt::Display;
+use std::future::Future;
+
+/// Applies a migration to a list of items in batches,
+/// continuing past failures so they can be retried later.
+#[derive(Debug, Clone)]
+pub struct MigrationRunner {
+	/// Number of items migrated concurrently
+	pub batch_size: usize,
+	/// Skip this many items, for resuming a previous run
+	pub skip: usize,
+}
+
+impl Default for MigrationRunner {
+	fn default() -> Self {
+		Self {
+			batch_size: 10,
+			skip: 0,
+		}
+	}
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MigrationProgress {
+	/// Total number of items, including skipped
+	pub total: usize,
+	/// Number of items processed so far, including skipped
+	pub processed: usize,
+	/// Items that failed along with the error
+	pub failed: Vec<(String, String)>,
+}
+
+impl std::fmt::Display for MigrationProgress {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"[{}/{}] {} failed",
+			self.processed,
+			self.total,
+			self.failed.len()
+		)
+	}
+}
+
+impl MigrationRunner {
+	pub fn new(batch_size: usize) -> Self {
+		Self {
+			batch_size,
+			..Default::default()
+		}
+	}
+
+	pub fn with_skip(mut self, skip: usize) -> Self {
+		self.skip = skip;
+		self
+	}
+
+	/// All documents in the collection with an outdated [SchemaVersion]
+	pub async fn outdated<T: HasDocId + SchemaVersion>(
+		collection: &dyn DocumentCollection<T>,
+	) -> Result<Vec<T>> {
+		collection
+			.find()
+			.filter(T::outdated_filter())
+			.send()
+			.await?
+			.try_collect()
+			.await
+	}
+
+	/// Eagerly write back all outdated documents, which have been upgraded
+	/// on read by [HasDocId::from_stored]. Returns the number of documents written.
+	/// [MemoryCollection] upgrades all documents on load so this will be a noop.
+	pub async fn upgrade_stored<T: HasDocId + SchemaVersion>(
+		collection: &dyn DocumentCollection<T>,
+	) -> Result<usize> {
+		let docs = Self::outdated(collection).await?;
+		collection.insert_many(&docs).await?;
+		Ok(docs.len())
+	}
+
+	/// Run the migration for each item, calling `on_progress` after each batch.
+	pub async fn run<I, Fut>(
+		&self,
+		items: Vec<I>,
+		migrate: impl Fn(I) -> Fut,
+		on_progress: impl Fn(&MigrationProgress),
+	) -> MigrationProgress
+	where
+		I: Display,
+		Fut: Future<Output = Result<()>>,
+	{
+		let mut progress = MigrationProgress {
+			total: items.len(),
+			processed: self.skip.min(items.len()),
+			failed: Vec::new(),
+		};
+		let mut items = items.into_iter().skip(self.skip).peekable();
+		while items.peek().is_some() {
+			let batch = items
+				.by_ref()
+				.take(self.batch_size.max(1))
+				.map(|item| {
+					let name = item.to_string();
+					let fut = migrate(item);
+					async move { (name, fut.await) }
+				})
+				.collect::<Vec<_>>();
+			for (name, result) in futures::future::join_all(batch).await {
+				progress.processed += 1;
+				if let Err(err) = result {
+					progress.failed.push((name, err.to_string()));
+				}
+			}
+			on_progress(&progress);
+		}
+		progress
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use sweet::*;
+
+	#[tokio::test]
+	async fn works() -> Result<()> {
+		let progress = MigrationRunner::new(2)
+			.with_skip(1)
+			.run(
+				vec![0, 1, 2, 3, 4],
+				|item| async move {
+					if item == 3 {
+						anyhow::bail!("bad item");
+					}
+					Ok(())
+				},
+				|_| {},
+			)
+			.await;
+		expect(progress.total).to_be(5)?;
+		expect(progress.processed).to_be(5)?;
+		expect(&progress.failed)
+			.to_be(&vec![("3".to_string(), "bad item".to_string())])?;
+
+		Ok(())
+	}
+}
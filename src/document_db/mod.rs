@@ -1,3 +1,6 @@
+pub mod change_event;
+#[allow(unused_imports)]
+pub use self::change_event::*;
 pub mod doc_id;
 #[allow(unused_imports)]
 pub use self::doc_id::*;
@@ -13,15 +16,33 @@ pub use self::document_db::*;
 pub mod document_stream;
 #[allow(unused_imports)]
 pub use self::document_stream::*;
+pub mod index_declaration;
+#[allow(unused_imports)]
+pub use self::index_declaration::*;
 pub mod memory_collection;
 #[allow(unused_imports)]
 pub use self::memory_collection::*;
 pub mod memory_db;
 #[allow(unused_imports)]
 pub use self::memory_db::*;
+pub mod migration_runner;
+#[allow(unused_imports)]
+pub use self::migration_runner::*;
 pub mod mongo_collection;
 #[allow(unused_imports)]
 pub use self::mongo_collection::*;
 pub mod mongo_db;
 #[allow(unused_imports)]
 pub use self::mongo_db::*;
+pub mod named_collection;
+#[allow(unused_imports)]
+pub use self::named_collection::*;
+pub mod schema_version;
+#[allow(unused_imports)]
+pub use self::schema_version::*;
+pub mod unit_of_work;
+#[allow(unused_imports)]
+pub use self::unit_of_work::*;
+pub mod update_document;
+#[allow(unused_imports)]
+pub use self::update_document::*;
@@ -1,16 +1,24 @@
 use super::doc_id::DocId;
 use crate::prelude::*;
 use anyhow::Result;
+use futures::StreamExt;
+use futures::TryStreamExt;
 use mongodb::bson::doc;
+use mongodb::bson::to_document;
+use mongodb::bson::Bson;
 use mongodb::bson::Document;
+use mongodb::change_stream::event::ChangeStreamEvent;
+use mongodb::change_stream::event::OperationType;
+use mongodb::options::FullDocumentType;
 
 #[async_trait::async_trait]
 impl<T: HasDocId> DocumentCollection<T> for mongodb::Collection<T> {
 	fn name(&self) -> &str { mongodb::Collection::<T>::name(self) }
+	/// Reads raw documents so they can be upgraded, see [HasDocId::from_stored]
 	async fn get(&self, id: &DocId) -> Result<Option<T>> {
 		let filter = doc! { "_id": id };
-		let doc = mongodb::Collection::<T>::find_one(self, filter).await?;
-		Ok(doc)
+		let doc = self.clone_with_type::<Document>().find_one(filter).await?;
+		doc.map(T::from_stored).transpose()
 	}
 	fn find(&self) -> FindBuilder<T> { FindBuilder::new(self) }
 	async fn send_find(
@@ -18,8 +26,13 @@ impl<T: HasDocId> DocumentCollection<T> for mongodb::Collection<T> {
 		document: Document,
 		skip: Option<u64>,
 		limit: Option<i64>,
+		sort: Option<Document>,
 	) -> Result<DocumentStream<T>> {
-		let mut stream = mongodb::Collection::<T>::find(self, document);
+		let raw = self.clone_with_type::<Document>();
+		let mut stream = raw.find(document);
+		if let Some(sort) = sort {
+			stream = stream.sort(sort);
+		}
 		if let Some(limit) = limit {
 			stream = stream.limit(limit);
 		}
@@ -51,14 +64,7 @@ impl<T: HasDocId> DocumentCollection<T> for mongodb::Collection<T> {
 	}
 	async fn insert_many(&self, docs: &Vec<T>) -> Result<Vec<DocId>> {
 		let futs = docs.iter().map(|doc| self.insert(doc));
-		let ids = futures::future::try_join_all(futs).await?;
-
-		// let result = mongodb::Collection::update_many(self, docs).await?;
-		// let mut ids = Vec::with_capacity(result.inserted_ids.len());
-		// for (i, id) in result.inserted_ids.iter() {
-		// 	ids[*i] = id.clone().into();
-		// }
-		Ok(ids)
+		futures::future::try_join_all(futs).await
 	}
 
 	async fn remove(&self, id: &DocId) -> Result<bool> {
@@ -67,8 +73,149 @@ impl<T: HasDocId> DocumentCollection<T> for mongodb::Collection<T> {
 		Ok(result.deleted_count == 1)
 	}
 
+	async fn update_one(
+		&self,
+		id: &DocId,
+		update: Document,
+		revision: Option<u64>,
+	) -> Result<bool> {
+		let filter = revision_filter(id, revision);
+		let update = revision_update(update, revision)?;
+		let result =
+			mongodb::Collection::<T>::update_one(self, filter, update).await?;
+		Ok(result.matched_count == 1)
+	}
+
+	async fn replace_one(&self, doc: &T, revision: Option<u64>) -> Result<bool> {
+		let filter = revision_filter(&doc.doc_id(), revision);
+		let mut replacement = to_document(doc)?;
+		if let Some(revision) = revision {
+			replacement.insert(REVISION_FIELD, (revision + 1) as i64);
+		}
+		let result = self
+			.clone_with_type::<Document>()
+			.replace_one(filter, replacement)
+			.await?;
+		Ok(result.matched_count == 1)
+	}
+
+	async fn update_many(
+		&self,
+		filter: Document,
+		update: Document,
+	) -> Result<u64> {
+		let result =
+			mongodb::Collection::<T>::update_many(self, filter, update).await?;
+		Ok(result.modified_count)
+	}
+
+	/// Sends each write in order, stopping at the first error.
+	/// Earlier writes are not rolled back, see [DocumentDb::commit].
+	async fn bulk_write(&self, writes: Vec<BulkWrite<T>>) -> Result<u64> {
+		let raw = self.clone_with_type::<Document>();
+		let mut modified = 0;
+		for write in writes {
+			modified += match write {
+				BulkWrite::Upsert(doc) => {
+					let filter = doc.doc_id().to_document();
+					let result = raw
+						.replace_one(filter, to_document(&doc)?)
+						.upsert(true)
+						.await?;
+					result.modified_count + result.upserted_id.is_some() as u64
+				}
+				BulkWrite::UpdateMany { filter, update } => {
+					raw.update_many(filter, update).await?.modified_count
+				}
+				BulkWrite::Remove(id) => {
+					raw.delete_one(id.to_document()).await?.deleted_count
+				}
+			};
+		}
+		Ok(modified)
+	}
+
+	async fn group_count(
+		&self,
+		filter: Document,
+		field: &str,
+		limit: Option<i64>,
+	) -> Result<Vec<GroupCount>> {
+		let mut pipeline = vec![
+			doc! { "$match": filter },
+			// non-array values are treated as a single item array
+			doc! { "$unwind": format!("${field}") },
+			doc! { "$group": { "_id": format!("${field}"), "count": { "$sum": 1 } } },
+			doc! { "$sort": { "count": -1, "_id": 1 } },
+		];
+		if let Some(limit) = limit {
+			pipeline.push(doc! { "$limit": limit });
+		}
+		let groups = mongodb::Collection::<T>::aggregate(self, pipeline)
+			.await?
+			.try_collect::<Vec<_>>()
+			.await?
+			.into_iter()
+			.map(|mut group| {
+				let count = match group.get("count") {
+					Some(Bson::Int32(count)) => *count as u64,
+					Some(Bson::Int64(count)) => *count as u64,
+					other => anyhow::bail!("unexpected group count: {:?}", other),
+				};
+				let value = group.remove("_id").unwrap_or(Bson::Null);
+				Ok(GroupCount { value, count })
+			})
+			.collect::<Result<Vec<_>>>()?;
+		Ok(groups)
+	}
+
+	async fn distinct(&self, filter: Document, field: &str) -> Result<Vec<Bson>> {
+		let values =
+			mongodb::Collection::<T>::distinct(self, field, filter).await?;
+		Ok(values)
+	}
+
+	/// Opens a change stream, which requires a replica set or sharded cluster.
+	/// Events other than inserts, updates, replacements and deletes are skipped.
+	async fn watch(&self) -> Result<ChangeStream<T>> {
+		let stream = self
+			.clone_with_type::<Document>()
+			.watch()
+			.full_document(FullDocumentType::UpdateLookup)
+			.await?
+			.filter_map(|event| async move {
+				match event {
+					Ok(event) => to_change_event(event).transpose(),
+					Err(err) => Some(Err(err.into())),
+				}
+			});
+		Ok(stream.boxed())
+	}
+
 	async fn clear(&self) -> Result<()> {
 		mongodb::Collection::<T>::delete_many(self, doc! {}).await?;
 		Ok(())
 	}
 }
+
+fn to_change_event<T: HasDocId>(
+	event: ChangeStreamEvent<Document>,
+) -> Result<Option<ChangeEvent<T>>> {
+	let kind = match event.operation_type {
+		OperationType::Insert => ChangeKind::Insert,
+		OperationType::Update | OperationType::Replace => ChangeKind::Update,
+		OperationType::Delete => ChangeKind::Remove,
+		_ => return Ok(None),
+	};
+	let Some(key) = event.document_key else {
+		anyhow::bail!("change event missing document key");
+	};
+	let id = match key.get("_id") {
+		Some(Bson::String(id)) => DocId::new(id),
+		Some(other) => other.clone().into(),
+		None => anyhow::bail!("change event missing _id"),
+	};
+	// the document may have been removed before an update was looked up
+	let doc = event.full_document.map(T::from_stored).transpose()?;
+	Ok(Some(ChangeEvent { kind, id, doc }))
+}
@@ -1,24 +1,48 @@
 use crate::prelude::*;
 use anyhow::Result;
 use mongodb::bson::doc;
+use mongodb::bson::Document;
+use mongodb::error::TRANSIENT_TRANSACTION_ERROR;
+use mongodb::error::UNKNOWN_TRANSACTION_COMMIT_RESULT;
 use mongodb::options::ClientOptions;
 use mongodb::options::ServerApi;
 use mongodb::options::ServerApiVersion;
 use mongodb::Client;
-use mongodb::Collection;
+use mongodb::ClientSession;
 use mongodb::Database;
+use std::sync::Arc;
+
+/// Maximum attempts of a transaction, and of each commit,
+/// see [MongoDb::commit]
+pub const MAX_TRANSACTION_ATTEMPTS: u32 = 5;
 
 #[derive(Debug, Clone)]
 pub struct MongoDb {
 	client: Client,
 	database: Database,
-	scenes: Collection<SceneDoc>,
-	crates: Collection<CrateDoc>,
 }
 
 impl MongoDb {
+	/// Connect using the `MONGODB_CLIENT` connection string.
+	/// Indexes are ensured if `MONGODB_ENSURE_INDEXES` is set.
 	pub async fn new(env: ApiEnvironment) -> Result<Self> {
 		let conn_str = std::env::var("MONGODB_CLIENT")?;
+		let database = match env {
+			ApiEnvironment::Local => {
+				unimplemented!("Local MongoDb not implemented")
+			}
+			ApiEnvironment::Staging => "db_staging",
+			ApiEnvironment::Prod => "db_prod",
+		};
+		let this = Self::connect(&conn_str, database).await?;
+		if std::env::var("MONGODB_ENSURE_INDEXES").is_ok() {
+			this.ensure_indexes().await?;
+		}
+		Ok(this)
+	}
+
+	/// Connect to a specific database, ie a local mongod for testing
+	pub async fn connect(conn_str: &str, database: &str) -> Result<Self> {
 		let mut client_options = ClientOptions::parse(conn_str).await?;
 		// Set the server_api field of the client_options object to set the version of the Stable API on the client
 		let server_api =
@@ -30,30 +54,200 @@ impl MongoDb {
 			.database("admin")
 			.run_command(doc! {"ping": 1})
 			.await?;
+		let database = client.database(database);
+		Ok(Self { client, database })
+	}
 
-		let database = match env {
-			ApiEnvironment::Local => {
-				unimplemented!("Local MongoDb not implemented")
-			}
-			ApiEnvironment::Staging => client.database("db_staging"),
-			ApiEnvironment::Prod => client.database("db_prod"),
-		};
+	pub fn client(&self) -> &Client { &self.client }
 
-		Ok(Self {
-			client,
-			scenes: database.collection("scenes"),
-			crates: database.collection("crates"),
-			database,
-		})
+	pub fn collection<T: NamedCollection>(
+		&self,
+	) -> Arc<dyn DocumentCollection<T>> {
+		Arc::new(self.database.collection::<T>(T::COLLECTION_NAME))
 	}
 
-	pub fn client(&self) -> &Client { &self.client }
+	/// Create the indexes declared by [NamedCollection::indexes]
+	pub async fn create_indexes(&self, info: &CollectionInfo) -> Result<()> {
+		let indexes = (info.indexes)()
+			.iter()
+			.map(|index| index.to_index_model())
+			.collect::<Vec<_>>();
+		if indexes.is_empty() {
+			return Ok(());
+		}
+		self.database
+			.collection::<Document>(info.name)
+			.create_indexes(indexes)
+			.await?;
+		Ok(())
+	}
+
+	/// Run the query planner for a find, see [QueryExplain::is_collection_scan]
+	pub async fn explain(
+		&self,
+		collection: &str,
+		filter: Document,
+	) -> Result<QueryExplain> {
+		let explain = self
+			.database
+			.run_command(doc! {
+				"explain": { "find": collection, "filter": filter },
+				"verbosity": "queryPlanner"
+			})
+			.await?;
+		let plan = explain
+			.get_document("queryPlanner")?
+			.get_document("winningPlan")?
+			.clone();
+		Ok(QueryExplain { plan })
+	}
+
+	async fn apply(
+		&self,
+		op: WriteOp,
+		session: &mut ClientSession,
+	) -> mongodb::error::Result<()> {
+		match op {
+			WriteOp::Insert { collection, id, doc } => {
+				self.database
+					.collection::<Document>(&collection)
+					.update_one(id.to_document(), doc! { "$set": doc })
+					.upsert(true)
+					.session(session)
+					.await?;
+			}
+			WriteOp::Remove { collection, id } => {
+				self.database
+					.collection::<Document>(&collection)
+					.delete_one(id.to_document())
+					.session(session)
+					.await?;
+			}
+			WriteOp::UpdateMany {
+				collection,
+				filter,
+				update,
+			} => {
+				self.database
+					.collection::<Document>(&collection)
+					.update_many(filter, update)
+					.session(session)
+					.await?;
+			}
+		}
+		Ok(())
+	}
 	pub fn database(&self) -> &Database { &self.database }
 }
 
+#[async_trait::async_trait]
 impl DocumentDb for MongoDb {
-	fn scenes(&self) -> &dyn DocumentCollection<SceneDoc> { &self.scenes }
-	fn crates(&self) -> &dyn DocumentCollection<CrateDoc> { &self.crates }
+	fn backend(&self) -> DocumentDbBackend<'_> {
+		DocumentDbBackend::Mongo(self)
+	}
+
+	/// Uses a multi-document transaction, aborted if any write fails.
+	/// Transactions require a replica set or sharded cluster,
+	/// a standalone mongod will return an error.
+	///
+	/// The transaction is retried on a `TransientTransactionError`
+	/// and the commit on an `UnknownTransactionCommitResult`,
+	/// at most [MAX_TRANSACTION_ATTEMPTS] times each.
+	async fn commit(&self, work: UnitOfWork) -> Result<()> {
+		if work.is_empty() {
+			return Ok(());
+		}
+		let mut session = self.client.start_session().await?;
+		let mut attempts = 0;
+		'transaction: loop {
+			attempts += 1;
+			let can_retry = |err: &mongodb::error::Error, label: &str| {
+				err.contains_label(label) && attempts < MAX_TRANSACTION_ATTEMPTS
+			};
+			session.start_transaction().await?;
+			for op in work.ops.iter() {
+				if let Err(err) = self.apply(op.clone(), &mut session).await {
+					session.abort_transaction().await.ok();
+					if can_retry(&err, TRANSIENT_TRANSACTION_ERROR) {
+						continue 'transaction;
+					}
+					return Err(err.into());
+				}
+			}
+			let mut commit_attempts = 0;
+			loop {
+				commit_attempts += 1;
+				match session.commit_transaction().await {
+					Ok(()) => return Ok(()),
+					Err(err)
+						if err.contains_label(UNKNOWN_TRANSACTION_COMMIT_RESULT)
+							&& commit_attempts < MAX_TRANSACTION_ATTEMPTS =>
+					{
+						continue;
+					}
+					Err(err)
+						if can_retry(&err, TRANSIENT_TRANSACTION_ERROR) =>
+					{
+						continue 'transaction;
+					}
+					Err(err) => return Err(err.into()),
+				}
+			}
+		}
+	}
+
+	async fn ensure_indexes(&self) -> Result<()> {
+		for info in COLLECTIONS.iter() {
+			self.create_indexes(info).await?;
+		}
+		Ok(())
+	}
+
+	/// Empties every collection in the database, keeping indexes
+	async fn clear(&self) -> Result<()> {
+		for name in self.database.list_collection_names().await? {
+			self.database
+				.collection::<Document>(&name)
+				.delete_many(doc! {})
+				.await?;
+		}
+		Ok(())
+	}
+}
+
+
+/// The winning plan of a query, from [MongoDb::explain]
+#[derive(Debug, Clone)]
+pub struct QueryExplain {
+	pub plan: Document,
+}
+
+impl QueryExplain {
+	/// Names of each stage in the plan, ie `FETCH`, `IXSCAN`
+	pub fn stages(&self) -> Vec<String> {
+		let mut stages = Vec::new();
+		collect_stages(&self.plan, &mut stages);
+		stages
+	}
+
+	/// The query reads every document instead of using an index
+	pub fn is_collection_scan(&self) -> bool {
+		self.stages().iter().any(|stage| stage == "COLLSCAN")
+	}
+}
+
+fn collect_stages(plan: &Document, stages: &mut Vec<String>) {
+	if let Ok(stage) = plan.get_str("stage") {
+		stages.push(stage.to_string());
+	}
+	if let Ok(input) = plan.get_document("inputStage") {
+		collect_stages(input, stages);
+	}
+	if let Ok(inputs) = plan.get_array("inputStages") {
+		for input in inputs.iter().filter_map(|input| input.as_document()) {
+			collect_stages(input, stages);
+		}
+	}
 }
 
 
@@ -64,6 +258,7 @@ mod test {
 	use mongodb::bson::doc;
 	use mongodb::bson::Document;
 	use mongodb::Collection;
+	use sweet::*;
 
 	#[tokio::test]
 	#[ignore = "hits mongodb"]
@@ -79,4 +274,26 @@ mod test {
 
 		Ok(())
 	}
+
+	#[tokio::test]
+	#[ignore = "hits local mongod"]
+	async fn hot_queries_use_indexes() -> Result<()> {
+		let db = MongoDb::connect("mongodb://localhost:27017", "db_test").await?;
+		db.ensure_indexes().await?;
+		for (collection, filter) in [
+			(SceneDoc::COLLECTION_NAME, doc! {
+				"scene_id.crate_id.name": "bevyhub_template",
+				"scene_id.crate_id.version": "0.0.1-rc.1"
+			}),
+			(SceneDoc::COLLECTION_NAME, doc! { "is_latest": true }),
+			(CrateDoc::COLLECTION_NAME, doc! { "crate_id.name": "bevyhub" }),
+			(IngestJob::COLLECTION_NAME, doc! { "status": "pending" }),
+		] {
+			let explain = db.explain(collection, filter.clone()).await?;
+			expect(explain.is_collection_scan())
+				.to_be_false()
+				.map_err(|err| anyhow::anyhow!("{collection} {filter}: {err}"))?;
+		}
+		Ok(())
+	}
 }
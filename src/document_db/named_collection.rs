@@ -0,0 +1,36 @@
+use crate::prelude::*;
+
+/// A document type stored in its own collection,
+/// opened with [DocumentDbExt::collection].
+pub trait NamedCollection: HasDocId {
+	const COLLECTION_NAME: &'static str;
+
+	/// Indexes created by [DocumentDb::ensure_indexes],
+	/// the memory backend only uses the text index for `$text` filters.
+	fn indexes() -> Vec<IndexDeclaration> { Vec::new() }
+}
+
+/// Every [NamedCollection], add new document types here
+/// so their indexes are created.
+pub const COLLECTIONS: &[CollectionInfo] = &[
+	CollectionInfo::of::<SceneDoc>(),
+	CollectionInfo::of::<CrateDoc>(),
+	CollectionInfo::of::<IngestJob>(),
+	CollectionInfo::of::<DiscoveredCrate>(),
+];
+
+/// The untyped parts of a [NamedCollection], see [COLLECTIONS]
+#[derive(Debug, Clone, Copy)]
+pub struct CollectionInfo {
+	pub name: &'static str,
+	pub indexes: fn() -> Vec<IndexDeclaration>,
+}
+
+impl CollectionInfo {
+	pub const fn of<T: NamedCollection>() -> Self {
+		Self {
+			name: T::COLLECTION_NAME,
+			indexes: T::indexes,
+		}
+	}
+}
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use mongodb::bson::doc;
+use mongodb::bson::Bson;
+use mongodb::bson::Document;
+use serde::de::DeserializeOwned;
+
+/// Documents that record the schema version they were written with,
+/// so that outdated documents can be found and migrated.
+pub trait SchemaVersion {
+	/// The current schema version, increment this whenever
+	/// the stored shape of the document changes.
+	/// Documents written before versioning was added have a version of `0`.
+	const SCHEMA_VERSION: u32;
+	fn schema_version(&self) -> u32;
+
+	/// Registry of upgrades for older stored documents, applied in order by
+	/// [upgrade_document]. Versions that only add optional or
+	/// `#[serde(default)]` fields dont need an upgrade.
+	fn upgrades() -> Vec<SchemaUpgrade> { Vec::new() }
+
+	fn is_outdated(&self) -> bool {
+		self.schema_version() < Self::SCHEMA_VERSION
+	}
+
+	/// Filter for documents not matching the current version,
+	/// including those with no `schema_version` field.
+	fn outdated_filter() -> Document {
+		// serde serializes u32 as Int64, use the same so the
+		// MemoryCollection comparison matches
+		doc! { "schema_version": { "$ne": Self::SCHEMA_VERSION as i64 } }
+	}
+}
+
+/// Upgrades a raw stored document from `from_version` to `from_version + 1`
+#[derive(Clone)]
+pub struct SchemaUpgrade {
+	pub from_version: u32,
+	pub upgrade: fn(&mut Document) -> Result<()>,
+}
+
+impl SchemaUpgrade {
+	pub fn new(
+		from_version: u32,
+		upgrade: fn(&mut Document) -> Result<()>,
+	) -> Self {
+		Self {
+			from_version,
+			upgrade,
+		}
+	}
+}
+
+/// The `schema_version` of a raw stored document, or `0` if missing
+pub fn stored_schema_version(doc: &Document) -> u32 {
+	match doc.get("schema_version") {
+		Some(Bson::Int32(val)) => *val as u32,
+		Some(Bson::Int64(val)) => *val as u32,
+		_ => 0,
+	}
+}
+
+/// Apply all upgrades from the stored version to the current one,
+/// then deserialize the document.
+pub fn upgrade_document<T: SchemaVersion + DeserializeOwned>(
+	mut doc: Document,
+) -> Result<T> {
+	let version = stored_schema_version(&doc);
+	if version < T::SCHEMA_VERSION {
+		let mut upgrades = T::upgrades()
+			.into_iter()
+			.filter(|upgrade| {
+				upgrade.from_version >= version
+					&& upgrade.from_version < T::SCHEMA_VERSION
+			})
+			.collect::<Vec<_>>();
+		upgrades.sort_by_key(|upgrade| upgrade.from_version);
+		for upgrade in upgrades {
+			(upgrade.upgrade)(&mut doc).map_err(|err| {
+				anyhow::anyhow!(
+					"failed to upgrade document from schema version {}: {}",
+					upgrade.from_version,
+					err
+				)
+			})?;
+		}
+		doc.insert("schema_version", T::SCHEMA_VERSION as i64);
+	}
+	Ok(mongodb::bson::from_document(doc)?)
+}
@@ -0,0 +1,170 @@
+use crate::prelude::*;
+use anyhow::Result;
+use mongodb::bson::to_document;
+use mongodb::bson::Document;
+
+/// A list of writes across collections to be committed all-or-nothing
+/// with [DocumentDb::commit].
+#[derive(Debug, Default, Clone)]
+pub struct UnitOfWork {
+	pub ops: Vec<WriteOp>,
+}
+
+/// A single raw write, stored as bson so that writes
+/// to collections of different types can be queued together.
+#[derive(Debug, Clone)]
+pub enum WriteOp {
+	/// Insert or replace a document
+	Insert {
+		collection: String,
+		id: DocId,
+		doc: Document,
+	},
+	Remove {
+		collection: String,
+		id: DocId,
+	},
+	/// A server side update, see [DocumentCollection::update_many]
+	UpdateMany {
+		collection: String,
+		filter: Document,
+		update: Document,
+	},
+}
+
+impl UnitOfWork {
+	pub fn new() -> Self { Self::default() }
+
+	pub fn is_empty(&self) -> bool { self.ops.is_empty() }
+
+	pub fn insert<T: HasDocId>(
+		&mut self,
+		collection: &dyn DocumentCollection<T>,
+		doc: &T,
+	) -> Result<()> {
+		self.ops.push(WriteOp::Insert {
+			collection: collection.name().to_string(),
+			id: doc.doc_id(),
+			doc: to_document(doc)?,
+		});
+		Ok(())
+	}
+
+	pub fn insert_many<T: HasDocId>(
+		&mut self,
+		collection: &dyn DocumentCollection<T>,
+		docs: &[T],
+	) -> Result<()> {
+		for doc in docs.iter() {
+			self.insert(collection, doc)?;
+		}
+		Ok(())
+	}
+
+	pub fn remove<T: HasDocId>(
+		&mut self,
+		collection: &dyn DocumentCollection<T>,
+		id: &DocId,
+	) {
+		self.ops.push(WriteOp::Remove {
+			collection: collection.name().to_string(),
+			id: id.clone(),
+		});
+	}
+
+	pub fn update_many<T: HasDocId>(
+		&mut self,
+		collection: &dyn DocumentCollection<T>,
+		filter: Document,
+		update: Document,
+	) {
+		self.ops.push(WriteOp::UpdateMany {
+			collection: collection.name().to_string(),
+			filter,
+			update,
+		});
+	}
+}
+
+impl WriteOp {
+	pub fn collection(&self) -> &str {
+		match self {
+			WriteOp::Insert { collection, .. } => collection,
+			WriteOp::Remove { collection, .. } => collection,
+			WriteOp::UpdateMany { collection, .. } => collection,
+		}
+	}
+
+	/// This write as a typed [BulkWrite],
+	/// used by databases without native transactions.
+	pub fn to_bulk_write<T: HasDocId>(&self) -> Result<BulkWrite<T>> {
+		Ok(match self {
+			WriteOp::Insert { doc, .. } => {
+				BulkWrite::Upsert(T::from_stored(doc.clone())?)
+			}
+			WriteOp::Remove { id, .. } => BulkWrite::Remove(id.clone()),
+			WriteOp::UpdateMany { filter, update, .. } => {
+				BulkWrite::UpdateMany {
+					filter: filter.clone(),
+					update: update.clone(),
+				}
+			}
+		})
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use futures::FutureExt;
+	use futures::StreamExt;
+	use mongodb::bson::doc;
+	use sweet::*;
+
+	#[tokio::test]
+	async fn commits() -> Result<()> {
+		let db = MemoryDb::temp();
+		let mut work = UnitOfWork::new();
+		let job = IngestJob::new(CrateId::bevyhub_template());
+		work.insert(&*db.collection::<IngestJob>(), &job)?;
+		db.commit(work).await?;
+		expect(db.collection::<IngestJob>().has(&job.doc_id()).await?).to_be_true()?;
+
+		let mut work = UnitOfWork::new();
+		work.remove(&*db.collection::<IngestJob>(), &job.doc_id());
+		db.commit(work).await?;
+		expect(db.collection::<IngestJob>().has(&job.doc_id()).await?).to_be_false()?;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn rolls_back() -> Result<()> {
+		let db = MemoryDb::temp();
+		let existing = IngestJob::new(CrateId::bevyhub_template());
+		db.collection::<IngestJob>().insert(&existing).await?;
+
+		let mut changes = db.collection::<IngestJob>().watch().await?;
+
+		let mut work = UnitOfWork::new();
+		work.remove(&*db.collection::<IngestJob>(), &existing.doc_id());
+		let job = IngestJob::new(CrateId::bevyhub_template_bad_version());
+		work.insert(&*db.collection::<IngestJob>(), &job)?;
+		// not a valid crate doc
+		work.ops.push(WriteOp::Insert {
+			collection: db.crates().name().to_string(),
+			id: DocId::new("foo"),
+			doc: doc! { "_id": "foo" },
+		});
+
+		expect(db.commit(work).await).to_be_err()?;
+		expect(db.collection::<IngestJob>().has(&existing.doc_id()).await?)
+			.to_be_true()?;
+		expect(db.collection::<IngestJob>().has(&job.doc_id()).await?).to_be_false()?;
+		expect(db.crates().has(&DocId::new("foo")).await?).to_be_false()?;
+		// watchers are not notified of rolled back writes
+		expect(changes.next().now_or_never()).to_be_none()?;
+		Ok(())
+	}
+}
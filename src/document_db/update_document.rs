@@ -0,0 +1,266 @@
+use crate::prelude::*;
+use anyhow::Result;
+use mongodb::bson::Bson;
+use mongodb::bson::Document;
+
+/// Apply a mongodb update document in place, this is used by
+/// in memory collections to mirror the server side operators.
+/// Supported operators are `$set`, `$unset`, `$inc`, `$push` and `$pull`,
+/// keys may be dotted paths.
+pub fn apply_update(doc: &mut Document, update: &Document) -> Result<()> {
+	for (operator, fields) in update.iter() {
+		let Bson::Document(fields) = fields else {
+			anyhow::bail!("{} expects a document, received {}", operator, fields);
+		};
+		for (path, value) in fields.iter() {
+			match operator.as_str() {
+				"$set" => {
+					with_parent(doc, path, true, |parent, key| {
+						parent.insert(key, value.clone());
+						Ok(())
+					})?;
+				}
+				"$unset" => {
+					with_parent(doc, path, false, |parent, key| {
+						parent.remove(key);
+						Ok(())
+					})?;
+				}
+				"$inc" => {
+					with_parent(doc, path, true, |parent, key| {
+						let sum = match parent.get(key) {
+							Some(current) => add_numbers(current, value)?,
+							None => add_numbers(&Bson::Int32(0), value)?,
+						};
+						parent.insert(key, sum);
+						Ok(())
+					})?;
+				}
+				"$push" => {
+					with_parent(doc, path, true, |parent, key| {
+						let items = match value {
+							Bson::Document(each) if each.contains_key("$each") => {
+								each.get_array("$each")?.clone()
+							}
+							value => vec![value.clone()],
+						};
+						match parent.get_mut(key) {
+							Some(Bson::Array(arr)) => arr.extend(items),
+							None => {
+								parent.insert(key, items);
+							}
+							Some(other) => {
+								anyhow::bail!("cannot $push to {}", other)
+							}
+						}
+						Ok(())
+					})?;
+				}
+				"$pull" => {
+					with_parent(doc, path, false, |parent, key| {
+						match parent.get_mut(key) {
+							Some(Bson::Array(arr)) => {
+								arr.retain(|item| !pull_matches(item, value))
+							}
+							None => {}
+							Some(other) => {
+								anyhow::bail!("cannot $pull from {}", other)
+							}
+						}
+						Ok(())
+					})?;
+				}
+				other => {
+					anyhow::bail!("unsupported update operator: {}", other)
+				}
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Like mongodb, a document condition matches any
+/// document containing its fields, otherwise values must be equal.
+fn pull_matches(item: &Bson, condition: &Bson) -> bool {
+	match (item, condition) {
+		(Bson::Document(item), Bson::Document(condition)) => {
+			compare_recursive(item, condition)
+		}
+		(item, condition) => item == condition,
+	}
+}
+
+/// Field used for optimistic concurrency by
+/// [DocumentCollection::update_one] and [DocumentCollection::replace_one].
+/// Documents using it should declare it as `#[serde(default)] revision: u64`.
+pub const REVISION_FIELD: &str = "revision";
+
+/// Matches the document, and its revision if provided
+pub fn revision_filter(id: &DocId, revision: Option<u64>) -> Document {
+	let mut filter = id.to_document();
+	if let Some(revision) = revision {
+		filter.insert(REVISION_FIELD, revision as i64);
+	}
+	filter
+}
+
+/// Increment the revision along with the update, if provided
+pub fn revision_update(
+	mut update: Document,
+	revision: Option<u64>,
+) -> Result<Document> {
+	if revision.is_some() {
+		let inc = update
+			.entry("$inc".to_string())
+			.or_insert_with(|| Document::new().into());
+		let Bson::Document(inc) = inc else {
+			anyhow::bail!("$inc expects a document, received {}", inc);
+		};
+		inc.insert(REVISION_FIELD, 1i64);
+	}
+	Ok(update)
+}
+
+/// Call `func` with the document containing the last key in a dotted path.
+/// If `create` is true missing parents are inserted, otherwise
+/// a missing parent is a no-op, matching mongodb.
+pub(crate) fn with_parent<R>(
+	doc: &mut Document,
+	path: &str,
+	create: bool,
+	func: impl FnOnce(&mut Document, &str) -> Result<R>,
+) -> Result<Option<R>> {
+	let Some((head, rest)) = path.split_once('.') else {
+		return func(doc, path).map(Some);
+	};
+	if create && !doc.contains_key(head) {
+		doc.insert(head, Document::new());
+	}
+	match doc.get_mut(head) {
+		Some(Bson::Document(child)) => with_parent(child, rest, create, func),
+		Some(_) if create => {
+			anyhow::bail!("cannot update {}, {} is not a document", path, head)
+		}
+		_ => Ok(None),
+	}
+}
+
+/// Add two numbers, keeping the narrowest type that fits the result.
+fn add_numbers(current: &Bson, value: &Bson) -> Result<Bson> {
+	let sum = match (current, value) {
+		(Bson::Int32(a), Bson::Int32(b)) => match a.checked_add(*b) {
+			Some(sum) => Bson::Int32(sum),
+			None => Bson::Int64(*a as i64 + *b as i64),
+		},
+		(Bson::Int32(a), Bson::Int64(b)) => Bson::Int64(*a as i64 + b),
+		(Bson::Int64(a), Bson::Int32(b)) => Bson::Int64(a + *b as i64),
+		(Bson::Int64(a), Bson::Int64(b)) => Bson::Int64(a + b),
+		(a, b) => match (as_f64(a), as_f64(b)) {
+			(Some(a), Some(b)) => Bson::Double(a + b),
+			_ => anyhow::bail!("cannot $inc {} by {}", a, b),
+		},
+	};
+	Ok(sum)
+}
+
+fn as_f64(value: &Bson) -> Option<f64> {
+	match value {
+		Bson::Int32(val) => Some(*val as f64),
+		Bson::Int64(val) => Some(*val as f64),
+		Bson::Double(val) => Some(*val),
+		_ => None,
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use mongodb::bson::doc;
+	use mongodb::bson::Document;
+	use sweet::*;
+
+	/// Run against each backend to check they behave the same
+	async fn update_semantics(
+		collection: &dyn DocumentCollection<Document>,
+	) -> Result<()> {
+		collection.clear().await?;
+		let id = DocId::new("foo");
+		collection
+			.insert(&doc! {
+				"_id": "foo",
+				"revision": 0i64,
+				"count": 1i64,
+				"tags": ["a", "b", "a"],
+				"items": [{ "name": "x", "size": 1 }, { "name": "y" }],
+				"meta": { "stale": true }
+			})
+			.await?;
+
+		for update in [
+			doc! { "$set": { "name": "bob", "meta.fresh": true } },
+			doc! { "$unset": { "meta.stale": "" } },
+			doc! { "$inc": { "count": 2i64, "views": 1i64 } },
+			doc! { "$push": { "tags": "c", "log": { "$each": [1, 2] } } },
+			doc! { "$pull": { "tags": "a", "items": { "name": "x" } } },
+		] {
+			expect(collection.update_one(&id, update, None).await?)
+				.to_be_true()?;
+		}
+		let doc = collection.get(&id).await?.unwrap();
+		expect(doc.get_str("name")?).to_be("bob")?;
+		expect(doc.get_document("meta")?).to_be(&doc! { "fresh": true })?;
+		expect(doc.get_i64("count")?).to_be(3)?;
+		expect(doc.get_i64("views")?).to_be(1)?;
+		expect(doc.get_array("tags")?).to_be(&vec!["b".into(), "c".into()])?;
+		expect(doc.get_array("log")?.len()).to_be(2)?;
+		expect(doc.get_array("items")?)
+			.to_be(&vec![doc! { "name": "y" }.into()])?;
+
+		let missing = DocId::new("bar");
+		expect(
+			collection
+				.update_one(&missing, doc! { "$set": { "name": "bill" } }, None)
+				.await?,
+		)
+		.to_be_false()?;
+
+		// revisions
+		let update = doc! { "$set": { "name": "bill" } };
+		expect(collection.update_one(&id, update.clone(), Some(0)).await?)
+			.to_be_true()?;
+		expect(collection.update_one(&id, update, Some(0)).await?)
+			.to_be_false()?;
+		expect(
+			collection
+				.replace_one(&doc! { "_id": "foo", "name": "ben" }, Some(1))
+				.await?,
+		)
+		.to_be_true()?;
+		let doc = collection.get(&id).await?.unwrap();
+		expect(doc.get_i64("revision")?).to_be(2)?;
+		expect(doc.contains_key("count")).to_be_false()?;
+		expect(
+			collection
+				.replace_one(&doc! { "_id": "foo" }, Some(1))
+				.await?,
+		)
+		.to_be_false()?;
+
+		collection.clear().await
+	}
+
+	#[tokio::test]
+	async fn memory() -> Result<()> {
+		update_semantics(&MemoryCollection::temp()).await
+	}
+
+	#[tokio::test]
+	#[ignore = "hits local mongod"]
+	async fn mongo() -> Result<()> {
+		let db = MongoDb::connect("mongodb://localhost:27017", "db_test").await?;
+		update_semantics(&db.database().collection::<Document>("test_updates"))
+			.await
+	}
+}
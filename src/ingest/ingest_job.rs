@@ -0,0 +1,95 @@
+use crate::prelude::*;
+use serde::Deserialize;
+use serde::Serialize;
+use ts_rs::TS;
+
+/// A queued request to unpack a crate version into the [DocumentDb],
+/// stored in the `ingest_jobs` collection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct IngestJob {
+	/// The unique database id in the form of stringified [CrateId],
+	/// `crates.io/{crate_name}/{version}`
+	_id: DocId,
+	/// The crate version to ingest
+	pub crate_id: CrateId,
+	pub status: IngestJobStatus,
+	/// Number of times `unpack_crate_to_db` has been attempted
+	pub attempts: u32,
+	/// The error from the most recent failed attempt
+	pub last_error: Option<String>,
+	/// Epoch timestamp
+	#[ts(type = "number")]
+	pub created_ms: u64,
+	/// Epoch timestamp
+	#[ts(type = "number")]
+	pub updated_ms: u64,
+	/// Incremented on every write by a worker,
+	/// so only one worker can claim the job, see [REVISION_FIELD].
+	#[serde(default)]
+	#[ts(type = "number")]
+	pub revision: u64,
+}
+
+impl HasDocId for IngestJob {
+	fn doc_id(&self) -> DocId { self._id.clone() }
+}
+
+impl NamedCollection for IngestJob {
+	const COLLECTION_NAME: &'static str = "ingest_jobs";
+	fn indexes() -> Vec<IndexDeclaration> {
+		vec![IndexDeclaration::ascending(&["status", "updated_ms"])]
+	}
+}
+
+impl IngestJob {
+	pub fn new(crate_id: CrateId) -> Self {
+		let now = epoch_millis();
+		Self {
+			_id: crate_id.into_doc_id(),
+			crate_id,
+			status: IngestJobStatus::Pending,
+			attempts: 0,
+			last_error: None,
+			created_ms: now,
+			updated_ms: now,
+			revision: 0,
+		}
+	}
+
+	pub fn set_status(&mut self, status: IngestJobStatus) {
+		self.status = status;
+		self.updated_ms = epoch_millis();
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[serde(rename_all = "snake_case")]
+pub enum IngestJobStatus {
+	/// Discovered but not yet attempted
+	Pending,
+	/// Currently being unpacked by a worker
+	Running,
+	Succeeded,
+	/// All retries have been exhausted
+	Failed,
+}
+
+impl IngestJobStatus {
+	pub fn all() -> [Self; 4] {
+		[Self::Pending, Self::Running, Self::Succeeded, Self::Failed]
+	}
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::Pending => "pending",
+			Self::Running => "running",
+			Self::Succeeded => "succeeded",
+			Self::Failed => "failed",
+		}
+	}
+}
+
+impl std::fmt::Display for IngestJobStatus {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.as_str())
+	}
+}
@@ -0,0 +1,49 @@
+use crate::prelude::*;
+use anyhow::Result;
+use mongodb::bson::doc;
+use serde::Deserialize;
+use serde::Serialize;
+use ts_rs::TS;
+
+/// Snapshot of the ingestion queue, returned by `/ingest/status`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct IngestStatus {
+	#[ts(type = "number")]
+	pub pending: u64,
+	#[ts(type = "number")]
+	pub running: u64,
+	#[ts(type = "number")]
+	pub succeeded: u64,
+	#[ts(type = "number")]
+	pub failed: u64,
+	/// The most recent failed jobs
+	pub recent_failures: Vec<IngestJob>,
+}
+
+impl Services {
+	pub async fn ingest_status(&self) -> Result<IngestStatus> {
+		let jobs = self.db().collection::<IngestJob>();
+		let count = |status: IngestJobStatus| {
+			jobs.count(doc! { "status": status.as_str() })
+		};
+		let recent_failures = jobs
+			.find()
+			.filter(doc! { "status": IngestJobStatus::Failed.as_str() })
+			.sort(doc! { "updated_ms": -1 })
+			.limit(MAX_RECENT_FAILURES)
+			.send()
+			.await?
+			.try_collect()
+			.await?;
+
+		Ok(IngestStatus {
+			pending: count(IngestJobStatus::Pending).await?,
+			running: count(IngestJobStatus::Running).await?,
+			succeeded: count(IngestJobStatus::Succeeded).await?,
+			failed: count(IngestJobStatus::Failed).await?,
+			recent_failures,
+		})
+	}
+}
+
+const MAX_RECENT_FAILURES: i64 = 20;
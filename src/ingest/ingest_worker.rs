@@ -0,0 +1,488 @@
+use crate::prelude::*;
+use anyhow::Result;
+use futures::StreamExt;
+use mongodb::bson::doc;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::time::Duration;
+use ts_rs::TS;
+
+/// Scene crates are those that depend on this crate.
+pub const BEVYHUB_CRATE_NAME: &str = "bevyhub";
+
+#[derive(Debug, Clone)]
+pub struct IngestConfig {
+	/// Crates to watch for new versions,
+	/// in addition to those already in the db.
+	pub crates: Vec<String>,
+	/// Maximum number of crates unpacked at the same time
+	pub concurrency: usize,
+	/// Maximum number of attempts before a job is marked as failed
+	pub max_attempts: u32,
+	/// Delay before the first retry, doubled for each subsequent retry
+	pub retry_delay: Duration,
+	/// Time between polls when running as a long-lived worker
+	pub poll_interval: Duration,
+	/// Running jobs not updated for this long are assumed to be abandoned,
+	/// ie by a worker that crashed, and are run again.
+	pub job_timeout: Duration,
+	/// Failed jobs are queued again by [IngestWorker::poll]
+	/// once they have not been updated for this long.
+	pub retry_failed_after: Duration,
+	/// If set, search for new scene crates before each poll
+	pub discovery: Option<DiscoverySource>,
+}
+
+impl Default for IngestConfig {
+	fn default() -> Self {
+		Self {
+			crates: Vec::new(),
+			concurrency: 4,
+			max_attempts: 3,
+			retry_delay: Duration::from_secs(1),
+			poll_interval: Duration::from_secs(300),
+			job_timeout: Duration::from_secs(900),
+			retry_failed_after: Duration::from_secs(60 * 60 * 24),
+			discovery: None,
+		}
+	}
+}
+
+impl IngestConfig {
+	/// Default config with watched crates read from
+	/// the comma seperated `INGEST_CRATES` env var,
+	/// and discovery enabled, see [DiscoverySource::from_env].
+	pub fn from_env() -> Self {
+		let crates = std::env::var("INGEST_CRATES")
+			.unwrap_or_default()
+			.split(',')
+			.map(|name| name.trim().to_string())
+			.filter(|name| !name.is_empty())
+			.collect();
+		Self {
+			crates,
+			discovery: Some(DiscoverySource::from_env()),
+			..Default::default()
+		}
+	}
+}
+
+/// Summary of a single poll and process cycle
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct IngestReport {
+	/// Jobs added to the queue by this poll
+	pub queued: Vec<CrateId>,
+	pub succeeded: Vec<CrateId>,
+	pub failed: Vec<CrateId>,
+}
+
+/// Watches the registry index for new versions of scene crates
+/// and unpacks them to the db.
+#[derive(Clone)]
+pub struct IngestWorker {
+	api: Services,
+	config: IngestConfig,
+}
+
+impl IngestWorker {
+	pub fn new(api: Services, config: IngestConfig) -> Self {
+		Self { api, config }
+	}
+
+	pub fn config(&self) -> &IngestConfig { &self.config }
+
+	/// Poll then process the queue once,
+	/// this is the entrypoint for scheduled invocations.
+	pub async fn run_once(&self) -> Result<IngestReport> {
+		let queued = self.poll().await?;
+		let mut report = self.process_queue().await?;
+		report.queued = queued.into_iter().map(|job| job.crate_id).collect();
+		tracing::info!(
+			"ingest - queued: {}, succeeded: {}, failed: {}",
+			report.queued.len(),
+			report.succeeded.len(),
+			report.failed.len()
+		);
+		Ok(report)
+	}
+
+	/// Call [Self::run_once] every `poll_interval`, logging any errors.
+	pub async fn run_forever(&self) -> Result<()> {
+		loop {
+			if let Err(err) = self.run_once().await {
+				tracing::error!("ingest - run failed: {:?}", err);
+			}
+			tokio::time::sleep(self.config.poll_interval).await;
+		}
+	}
+
+	/// Names of all crates configured, discovered or already in the db
+	pub async fn watched_crates(&self) -> Result<BTreeSet<String>> {
+		let mut names = self
+			.config
+			.crates
+			.iter()
+			.cloned()
+			.collect::<BTreeSet<_>>();
+		let crates = self
+			.api
+			.db()
+			.crates()
+			.find()
+			.send()
+			.await?
+			.try_collect()
+			.await?;
+		names.extend(crates.into_iter().map(|doc| doc.crate_id.name));
+		let discovered = self
+			.api
+			.db()
+			.collection::<DiscoveredCrate>()
+			.find()
+			.send()
+			.await?
+			.try_collect()
+			.await?;
+		names.extend(discovered.into_iter().map(|doc| doc.crate_name));
+		Ok(names)
+	}
+
+	/// Check the index of each watched crate for unyanked versions that
+	/// depend on bevyhub and have not been ingested or queued.
+	/// Returns the newly queued jobs.
+	pub async fn poll(&self) -> Result<Vec<IngestJob>> {
+		let mut queued = Vec::new();
+		if let Some(source) = &self.config.discovery {
+			if let Err(err) = self.api.discover_crates(source).await {
+				tracing::warn!("ingest - discovery failed: {:?}", err);
+			}
+			for crate_id in self.api.discovery_candidates().await? {
+				if let Some(job) = self.enqueue(crate_id).await? {
+					queued.push(job);
+				}
+			}
+		}
+		for crate_name in self.watched_crates().await? {
+			// one bad index shouldnt block the rest
+			let index = match self.api.registry().crate_index(&crate_name).await
+			{
+				Ok(index) => index,
+				Err(err) => {
+					tracing::warn!(
+						"ingest - failed to fetch index for {}: {:?}",
+						crate_name,
+						err
+					);
+					continue;
+				}
+			};
+			for version in index.into_iter().filter(depends_on_bevyhub) {
+				let version = match semver::Version::parse(&version.vers) {
+					Ok(version) => version,
+					Err(err) => {
+						tracing::warn!(
+							"ingest - skipping invalid version {} of {}: {:?}",
+							version.vers,
+							crate_name,
+							err
+						);
+						continue;
+					}
+				};
+				let crate_id = CrateId::new(&crate_name, version);
+				if let Some(job) = self.enqueue(crate_id).await? {
+					queued.push(job);
+				}
+			}
+		}
+		Ok(queued)
+	}
+
+	/// Add a job to the queue if the crate is not already ingested or queued.
+	/// Failed jobs are queued again after `retry_failed_after`.
+	pub async fn enqueue(&self, crate_id: CrateId) -> Result<Option<IngestJob>> {
+		let doc_id = crate_id.into_doc_id();
+		if self.api.db().crates().has(&doc_id).await? {
+			return Ok(None);
+		}
+		let retry_before = epoch_millis()
+			.saturating_sub(self.config.retry_failed_after.as_millis() as u64);
+		let jobs = self.api.db().collection::<IngestJob>();
+		match jobs.get(&doc_id).await? {
+			None => {
+				let job = IngestJob::new(crate_id);
+				jobs.insert(&job).await?;
+				Ok(Some(job))
+			}
+			Some(job)
+				if job.status == IngestJobStatus::Failed
+					&& job.updated_ms < retry_before =>
+			{
+				self.requeue(job).await
+			}
+			Some(_) => Ok(None),
+		}
+	}
+
+	/// Reset a job to pending with no attempts, regardless of its status.
+	/// Returns `None` if the job was updated by another worker in the meantime.
+	pub async fn requeue(
+		&self,
+		mut job: IngestJob,
+	) -> Result<Option<IngestJob>> {
+		job.attempts = 0;
+		job.last_error = None;
+		job.set_status(IngestJobStatus::Pending);
+		match self.save_job(&mut job).await? {
+			true => Ok(Some(job)),
+			false => Ok(None),
+		}
+	}
+
+	/// Write the status of a job, only if it has not been written
+	/// by another worker since it was read.
+	async fn save_job(&self, job: &mut IngestJob) -> Result<bool> {
+		let saved = self
+			.api
+			.db()
+			.collection::<IngestJob>()
+			.update_one(
+				&job.doc_id(),
+				doc! { "$set": {
+					"status": job.status.as_str(),
+					"attempts": job.attempts as i64,
+					"last_error": job.last_error.clone(),
+					"updated_ms": job.updated_ms as i64,
+				}},
+				Some(job.revision),
+			)
+			.await?;
+		if saved {
+			job.revision += 1;
+		}
+		Ok(saved)
+	}
+
+	/// Unpack all pending and abandoned jobs,
+	/// running at most `concurrency` at a time.
+	pub async fn process_queue(&self) -> Result<IngestReport> {
+		let abandoned_ms = epoch_millis()
+			.saturating_sub(self.config.job_timeout.as_millis() as u64);
+		let pending = self
+			.api
+			.db()
+			.collection::<IngestJob>()
+			.find()
+			.filter(doc! { "$or": [
+				{ "status": IngestJobStatus::Pending.as_str() },
+				{
+					"status": IngestJobStatus::Running.as_str(),
+					"updated_ms": { "$lt": abandoned_ms as i64 },
+				},
+			]})
+			.send()
+			.await?
+			.try_collect()
+			.await?;
+
+		let results = futures::stream::iter(pending)
+			.map(|job| async move {
+				let crate_id = job.crate_id.clone();
+				(crate_id, self.process_job(job).await)
+			})
+			.buffer_unordered(self.config.concurrency.max(1))
+			.collect::<Vec<_>>()
+			.await;
+
+		let mut report = IngestReport::default();
+		for (crate_id, result) in results {
+			match result {
+				Ok(Some(job)) if job.status == IngestJobStatus::Succeeded => {
+					report.succeeded.push(crate_id)
+				}
+				Ok(Some(_)) => report.failed.push(crate_id),
+				// claimed by another worker
+				Ok(None) => {}
+				Err(err) => {
+					tracing::error!(
+						"ingest - failed to update job for {}: {:?}",
+						crate_id,
+						err
+					);
+					report.failed.push(crate_id);
+				}
+			}
+		}
+		Ok(report)
+	}
+
+	/// Delay before the next attempt of a job that has failed `attempts` times
+	pub fn retry_backoff(&self, attempts: u32) -> Duration {
+		self.config.retry_delay * 2u32.pow(attempts.saturating_sub(1))
+	}
+
+	/// Claim the job then attempt to unpack the crate,
+	/// retrying with exponential backoff.
+	/// Returns `None` if the job was claimed by another worker.
+	/// Only db errors while updating the job are returned.
+	async fn process_job(
+		&self,
+		mut job: IngestJob,
+	) -> Result<Option<IngestJob>> {
+		loop {
+			// also refreshes `updated_ms` so the job is not reclaimed
+			job.set_status(IngestJobStatus::Running);
+			if !self.save_job(&mut job).await? {
+				return Ok(None);
+			}
+			job.attempts += 1;
+			match self.api.unpack_crate_to_db(&job.crate_id).await {
+				Ok(_) => {
+					job.last_error = None;
+					job.set_status(IngestJobStatus::Succeeded);
+					break;
+				}
+				Err(err) => {
+					tracing::warn!(
+						"ingest - attempt {} failed for {}: {:?}",
+						job.attempts,
+						job.crate_id,
+						err
+					);
+					job.last_error = Some(err.to_string());
+					if job.attempts >= self.config.max_attempts {
+						job.set_status(IngestJobStatus::Failed);
+						break;
+					}
+					tokio::time::sleep(self.retry_backoff(job.attempts)).await;
+				}
+			}
+		}
+		match self.save_job(&mut job).await? {
+			true => Ok(Some(job)),
+			false => Ok(None),
+		}
+	}
+}
+
+fn depends_on_bevyhub(version: &CrateIndexVersion) -> bool {
+	!version.yanked
+		&& version.deps.iter().any(|dep| dep.name == BEVYHUB_CRATE_NAME)
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use semver::Version;
+	use std::time::Duration;
+	use sweet::*;
+
+	fn crate_id(name: &str) -> CrateId {
+		CrateId::new(name, Version::new(0, 1, 0))
+	}
+
+	#[tokio::test]
+	async fn enqueue() -> Result<()> {
+		let api = Services::test(FakeRegistry::new());
+		let worker = IngestWorker::new(api.clone(), IngestConfig {
+			retry_failed_after: Duration::from_secs(60),
+			..Default::default()
+		});
+		let jobs = api.db().collection::<IngestJob>();
+
+		// already ingested
+		let manifest = toml::from_str::<CargoManifest>(
+			r#"
+			[package]
+			name = "ingested"
+			version = "0.1.0"
+			"#,
+		)?;
+		let crate_doc = CrateDoc::from_manifest(&manifest, None)?;
+		api.db().crates().insert(&crate_doc).await?;
+		expect(worker.enqueue(crate_id("ingested")).await?).to_be_none()?;
+		expect(worker.watched_crates().await?.contains("ingested"))
+			.to_be_true()?;
+
+		// already queued
+		expect(worker.enqueue(crate_id("foo")).await?).to_be_some()?;
+		expect(worker.enqueue(crate_id("foo")).await?).to_be_none()?;
+
+		// failed jobs are only queued again after the cooldown
+		let mut failed = IngestJob::new(crate_id("failed"));
+		failed.attempts = 3;
+		failed.last_error = Some("not found".into());
+		failed.set_status(IngestJobStatus::Failed);
+		jobs.insert(&failed).await?;
+		expect(worker.enqueue(crate_id("failed")).await?).to_be_none()?;
+		failed.updated_ms -= 120_000;
+		jobs.insert(&failed).await?;
+		let job = worker.enqueue(crate_id("failed")).await?.unwrap();
+		expect(job.status).to_be(IngestJobStatus::Pending)?;
+		expect(job.attempts).to_be(0)?;
+		expect(job.last_error.is_none()).to_be_true()?;
+		expect(jobs.get(&failed.doc_id()).await?.unwrap()).to_be(job)?;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn claims_once() -> Result<()> {
+		let api = Services::test(FakeRegistry::new());
+		let worker = IngestWorker::new(api.clone(), IngestConfig {
+			max_attempts: 1,
+			..Default::default()
+		});
+		let jobs = api.db().collection::<IngestJob>();
+		let job = worker.enqueue(crate_id("foo")).await?.unwrap();
+		let claimed = worker.process_job(job.clone()).await?.unwrap();
+		expect(claimed.status).to_be(IngestJobStatus::Failed)?;
+		// read before the first worker claimed it
+		expect(worker.process_job(job).await?).to_be_none()?;
+		expect(jobs.get(&claimed.doc_id()).await?.unwrap()).to_be(claimed)?;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn retries() -> Result<()> {
+		// the fake registry has no crates so every attempt fails
+		let api = Services::test(FakeRegistry::new());
+		let worker = IngestWorker::new(api.clone(), IngestConfig {
+			max_attempts: 3,
+			retry_delay: Duration::from_millis(1),
+			job_timeout: Duration::from_secs(60),
+			..Default::default()
+		});
+		expect(worker.retry_backoff(1)).to_be(Duration::from_millis(1))?;
+		expect(worker.retry_backoff(3)).to_be(Duration::from_millis(4))?;
+
+		let jobs = api.db().collection::<IngestJob>();
+		worker.enqueue(crate_id("pending")).await?;
+		// left running by a crashed worker
+		let mut abandoned = IngestJob::new(crate_id("abandoned"));
+		abandoned.set_status(IngestJobStatus::Running);
+		abandoned.updated_ms -= 120_000;
+		jobs.insert(&abandoned).await?;
+		// still running elsewhere
+		let mut running = IngestJob::new(crate_id("running"));
+		running.set_status(IngestJobStatus::Running);
+		jobs.insert(&running).await?;
+
+		let mut report = worker.process_queue().await?;
+		expect(report.succeeded.len()).to_be(0)?;
+		report.failed.sort_by(|a, b| a.name.cmp(&b.name));
+		expect(report.failed)
+			.to_be(vec![crate_id("abandoned"), crate_id("pending")])?;
+
+		let pending = crate_id("pending").into_doc_id();
+		let job = jobs.get(&pending).await?.unwrap();
+		expect(job.status).to_be(IngestJobStatus::Failed)?;
+		expect(job.attempts).to_be(3)?;
+		expect(job.last_error).to_be_some()?;
+		let job = jobs.get(&running.doc_id()).await?.unwrap();
+		expect(job.status).to_be(IngestJobStatus::Running)?;
+		Ok(())
+	}
+}
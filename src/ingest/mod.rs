@@ -0,0 +1,9 @@
+pub mod ingest_job;
+#[allow(unused_imports)]
+pub use self::ingest_job::*;
+pub mod ingest_status;
+#[allow(unused_imports)]
+pub use self::ingest_status::*;
+pub mod ingest_worker;
+#[allow(unused_imports)]
+pub use self::ingest_worker::*;
@@ -3,7 +3,9 @@
 pub mod services;
 pub mod cargo_registry;
 pub mod crate_doc;
+pub mod discovery;
 pub mod document_db;
+pub mod ingest;
 pub mod object_storage;
 pub mod scene_doc;
 pub mod server;
@@ -14,7 +16,9 @@ pub mod prelude {
 	pub use crate::services::*;
 	pub use crate::cargo_registry::*;
 	pub use crate::crate_doc::*;
+	pub use crate::discovery::*;
 	pub use crate::document_db::*;
+	pub use crate::ingest::*;
 	pub use crate::object_storage::*;
 	pub use crate::scene_doc::*;
 	pub use crate::server::layers::*;
@@ -1,12 +1,34 @@
 use bevyhub_api::prelude::*;
+use lambda_http::service_fn;
+use lambda_http::LambdaEvent;
 use std::env::set_var;
 
 /// AWS lambda entrypoint
+/// If `LAMBDA_MODE=ingest` this will run a single ingest cycle per invocation,
+/// for use with a scheduled trigger.
 #[tokio::main]
 async fn main() -> Result<(), lambda_http::Error> {
 	lambda_http::tracing::init_default_subscriber();
+	if std::env::var("LAMBDA_MODE").ok().as_deref() == Some("ingest") {
+		return run_ingest().await;
+	}
 	set_var("AWS_LAMBDA_HTTP_IGNORE_STAGE_IN_PATH", "true");
 	let app = server().await?;
 	tracing::info!("aand we're live!\nenv: {}", ApiEnvironment::default(),);
 	lambda_http::run(app).await
 }
+
+async fn run_ingest() -> Result<(), lambda_http::Error> {
+	let worker =
+		IngestWorker::new(Services::init().await?, IngestConfig::from_env());
+	tracing::info!("ingest worker live!\nenv: {}", ApiEnvironment::default(),);
+	lambda_http::lambda_runtime::run(service_fn(
+		|_event: LambdaEvent<serde_json::Value>| {
+			let worker = worker.clone();
+			async move {
+				worker.run_once().await.map_err(lambda_http::Error::from)
+			}
+		},
+	))
+	.await
+}
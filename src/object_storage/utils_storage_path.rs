@@ -6,4 +6,17 @@ pub mod storage_path {
 	pub fn unpkg_path(crate_id: &CrateId, path: &str) -> String {
 		format!("{}/{}/{}", UNPKG_DIR, crate_id.path(), path)
 	}
+	/// The directory where scene bundles are cached
+	pub const BUNDLE_DIR: &str = "bundles";
+	/// Path to the cached `.tar.gz` bundle of a scene.
+	pub fn bundle_path(scene_id: &SceneId) -> String {
+		format!("{}/{}.tar.gz", BUNDLE_DIR, scene_id.path())
+	}
+	/// The directory where rendered readmes are cached, seperate from
+	/// [UNPKG_DIR] so a crate cannot ship its own.
+	pub const README_DIR: &str = "readmes";
+	/// Path to the cached html of a crate README.
+	pub fn readme_html_path(crate_id: &CrateId) -> String {
+		format!("{}/{}.html", README_DIR, crate_id.path())
+	}
 }
@@ -0,0 +1,42 @@
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+use ts_rs::TS;
+
+/// Size and hash of a scene file, so clients can verify what they load
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct FileIntegrity {
+	/// Size in bytes
+	#[ts(type = "number")]
+	pub size: u64,
+	/// Hex encoded sha256 of the contents
+	pub sha256: String,
+}
+
+impl FileIntegrity {
+	pub fn new(bytes: &[u8]) -> Self {
+		Self {
+			size: bytes.len() as u64,
+			sha256: hex::encode(Sha256::digest(bytes)),
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use sweet::*;
+
+	#[test]
+	fn works() -> Result<()> {
+		let integrity = FileIntegrity::new(b"hello");
+		expect(integrity.size).to_be(5)?;
+		expect(integrity.sha256.as_str()).to_be(
+			"2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824",
+		)?;
+		Ok(())
+	}
+}
@@ -0,0 +1,237 @@
+use crate::prelude::*;
+use anyhow::Result;
+
+/// Default for [IncludePath::max_depth],
+/// overridden by the `SCENE_MAX_INCLUDE_DEPTH` env var
+pub const DEFAULT_MAX_INCLUDE_DEPTH: usize = 32;
+
+/// The chain of scenes being resolved, from the root scene down,
+/// used to detect cycles in includes and apps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncludePath {
+	path: Vec<SceneId>,
+	/// Maximum number of scenes in the path, including the root
+	pub max_depth: usize,
+}
+
+impl Default for IncludePath {
+	fn default() -> Self { Self::new(DEFAULT_MAX_INCLUDE_DEPTH) }
+}
+
+impl IncludePath {
+	pub fn new(max_depth: usize) -> Self {
+		Self {
+			path: Vec::new(),
+			max_depth,
+		}
+	}
+
+	pub fn from_env() -> Self {
+		let max_depth = std::env::var("SCENE_MAX_INCLUDE_DEPTH")
+			.ok()
+			.and_then(|val| val.parse().ok())
+			.unwrap_or(DEFAULT_MAX_INCLUDE_DEPTH);
+		Self::new(max_depth)
+	}
+
+	/// Extend the path with a scene.
+	/// # Errors
+	/// If the scene is already in the path or the path is too deep.
+	pub fn push(&self, scene_id: &SceneId) -> Result<Self> {
+		let mut path = self.path.clone();
+		path.push(scene_id.clone());
+		if self.path.contains(scene_id) {
+			return Err(IncludeError::Cycle(path).into());
+		}
+		if path.len() > self.max_depth {
+			return Err(IncludeError::TooDeep {
+				max_depth: self.max_depth,
+				path,
+			}
+			.into());
+		}
+		Ok(Self {
+			path,
+			max_depth: self.max_depth,
+		})
+	}
+
+	/// Check a tree resolved by another crate, which may include
+	/// a scene already in the path.
+	pub fn check_tree(&self, tree: &SceneIncludeTree) -> Result<()> {
+		let path = self.push(&tree.scene_id)?;
+		for child in tree.children.iter() {
+			path.check_tree(child)?;
+		}
+		Ok(())
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IncludeError {
+	/// The path ends with a scene that appears earlier in it
+	Cycle(Vec<SceneId>),
+	TooDeep {
+		max_depth: usize,
+		path: Vec<SceneId>,
+	},
+}
+
+impl std::fmt::Display for IncludeError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let join = |path: &[SceneId]| {
+			path.iter()
+				.map(|scene_id| scene_id.to_string())
+				.collect::<Vec<_>>()
+				.join(" -> ")
+		};
+		match self {
+			Self::Cycle(path) => {
+				write!(f, "scene include cycle: {}", join(path))
+			}
+			Self::TooDeep { max_depth, path } => write!(
+				f,
+				"scene includes exceed max depth of {max_depth}: {}",
+				join(path)
+			),
+		}
+	}
+}
+
+impl std::error::Error for IncludeError {}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use crate::scene_doc::test_utils::*;
+	use anyhow::Result;
+	use sweet::*;
+
+	fn include_error(err: anyhow::Error) -> IncludeError {
+		err.downcast::<IncludeError>().unwrap()
+	}
+
+	fn names(path: &[SceneId]) -> Vec<&str> {
+		path.iter().map(|scene_id| scene_id.scene_name()).collect()
+	}
+
+	#[tokio::test]
+	async fn intra_crate() -> Result<()> {
+		let api = Services::test(FakeRegistry::new());
+		let err = scene_docs(
+			&api,
+			r#"
+			[package]
+			name = "a"
+			version = "0.1.0"
+
+			[[package.metadata.scene]]
+			name = "first"
+			include = ["second"]
+
+			[[package.metadata.scene]]
+			name = "second"
+			include = ["first"]
+			"#,
+			&IncludePath::default(),
+		)
+		.await
+		.unwrap_err();
+		let IncludeError::Cycle(path) = include_error(err) else {
+			panic!("expected a cycle");
+		};
+		expect(names(&path)).to_be(vec!["first", "second", "first"])?;
+
+		let err = scene_docs(
+			&api,
+			r#"
+			[package]
+			name = "a"
+			version = "0.1.0"
+
+			[[package.metadata.scene]]
+			name = "first"
+			app = "first"
+			"#,
+			&IncludePath::default(),
+		)
+		.await
+		.unwrap_err();
+		expect(err.to_string().as_str())
+			.to_be("scene include cycle: a/first/0.1.0 -> a/first/0.1.0")?;
+
+		let manifest = r#"
+			[package]
+			name = "a"
+			version = "0.1.0"
+
+			[[package.metadata.scene]]
+			name = "first"
+			include = ["second"]
+
+			[[package.metadata.scene]]
+			name = "second"
+			include = ["third"]
+
+			[[package.metadata.scene]]
+			name = "third"
+			"#;
+		let err = scene_docs(&api, manifest, &IncludePath::new(2))
+			.await
+			.unwrap_err();
+		expect(matches!(include_error(err), IncludeError::TooDeep {
+			max_depth: 2,
+			..
+		}))
+		.to_be_true()?;
+		expect(scene_docs(&api, manifest, &IncludePath::new(3)).await)
+			.to_be_ok()?;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn cross_crate() -> Result<()> {
+		let api = Services::test(FakeRegistry::new());
+		let a_manifest = |include: &str| {
+			format!(
+				r#"
+				[package]
+				name = "a"
+				version = "0.1.0"
+
+				[[package.metadata.scene]]
+				name = "base"
+				include = [{include}]
+				"#
+			)
+		};
+		let path = IncludePath::default();
+		let docs = scene_docs(&api, &a_manifest(""), &path).await?;
+		api.db().scenes().insert_many(&docs).await?;
+		let docs = scene_docs(
+			&api,
+			r#"
+			[package]
+			name = "b"
+			version = "0.1.0"
+
+			[[package.metadata.scene]]
+			name = "other"
+			include = ["a/base"]
+			"#,
+			&path,
+		)
+		.await?;
+		api.db().scenes().insert_many(&docs).await?;
+
+		let err = scene_docs(&api, &a_manifest(r#""b/other""#), &path)
+			.await
+			.unwrap_err();
+		let IncludeError::Cycle(path) = include_error(err) else {
+			panic!("expected a cycle");
+		};
+		expect(names(&path)).to_be(vec!["base", "other", "base"])?;
+		Ok(())
+	}
+}
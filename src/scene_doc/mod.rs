@@ -1,18 +1,44 @@
+pub mod file_integrity;
+#[allow(unused_imports)]
+pub use self::file_integrity::*;
+pub mod include_path;
+#[allow(unused_imports)]
+pub use self::include_path::*;
 pub mod replication_config;
 #[allow(unused_imports)]
 pub use self::replication_config::*;
+pub mod resolved_scene_tree;
+#[allow(unused_imports)]
+pub use self::resolved_scene_tree::*;
 pub mod scene_app;
 #[allow(unused_imports)]
 pub use self::scene_app::*;
+pub mod scene_bundle;
+#[allow(unused_imports)]
+pub use self::scene_bundle::*;
+pub mod scene_dependents;
+#[allow(unused_imports)]
+pub use self::scene_dependents::*;
 pub mod scene_doc;
 #[allow(unused_imports)]
 pub use self::scene_doc::*;
 pub mod scene_doc_api;
 #[allow(unused_imports)]
 pub use self::scene_doc_api::*;
+pub mod scene_include_graph;
+#[allow(unused_imports)]
+pub use self::scene_include_graph::*;
 pub mod scene_include_tree;
 #[allow(unused_imports)]
 pub use self::scene_include_tree::*;
-pub mod set_latest_scenes_in_db;
+pub mod scene_facets;
+#[allow(unused_imports)]
+pub use self::scene_facets::*;
+pub mod scene_filter;
+#[allow(unused_imports)]
+pub use self::scene_filter::*;
+pub mod scene_metadata;
 #[allow(unused_imports)]
-pub use self::set_latest_scenes_in_db::*;
+pub use self::scene_metadata::*;
+#[cfg(test)]
+pub mod test_utils;
@@ -0,0 +1,186 @@
+use crate::prelude::*;
+use anyhow::Result;
+use base64::Engine;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use ts_rs::TS;
+
+/// Maximum total size of scene content in a [ResolvedSceneTree]
+pub const MAX_RESOLVED_BYTES: usize = 8 * 1024 * 1024;
+
+/// A [SceneDoc] with the content of every scene in its include tree
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct ResolvedScene {
+	pub doc: SceneDoc,
+	pub tree: ResolvedSceneTree,
+}
+
+/// A [SceneIncludeTree] node with the scene content embedded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct ResolvedSceneTree {
+	pub scene_id: SceneId,
+	pub file: SceneFile,
+	pub content: SceneContent,
+	pub children: Vec<ResolvedSceneTree>,
+}
+
+/// The contents of a scene file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SceneContent {
+	/// Json and ron scenes
+	Text { text: String },
+	/// Bsn scenes, base64 encoded
+	Base64 { base64: String },
+}
+
+/// Returned when a tree exceeds [MAX_RESOLVED_BYTES]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedTooLarge;
+
+impl std::fmt::Display for ResolvedTooLarge {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"scene content exceeds {} bytes, load the tree without resolve",
+			MAX_RESOLVED_BYTES
+		)
+	}
+}
+
+impl std::error::Error for ResolvedTooLarge {}
+
+impl Services {
+	/// Load the scene and the content of each scene it includes
+	pub async fn resolve_scene(
+		&self,
+		scene_id: &SceneId,
+	) -> Result<ResolvedScene> {
+		let doc = self.scene_doc(scene_id).await?;
+		let mut remaining = MAX_RESOLVED_BYTES;
+		let tree = resolve_tree(
+			self,
+			&doc.scene_include_tree,
+			&mut remaining,
+			&mut HashMap::new(),
+		)
+		.await?;
+		Ok(ResolvedScene { doc, tree })
+	}
+}
+
+/// Load each node in order, failing with [ResolvedTooLarge]
+/// once the `remaining` budget is used. Scenes included more than once
+/// are only loaded and counted once, using the `loaded` content.
+async fn resolve_tree(
+	api: &Services,
+	tree: &SceneIncludeTree,
+	remaining: &mut usize,
+	loaded: &mut HashMap<SceneId, SceneContent>,
+) -> Result<ResolvedSceneTree> {
+	let content = match loaded.get(&tree.scene_id) {
+		Some(content) => content.clone(),
+		None => {
+			let content = load_content(api, tree, remaining).await?;
+			loaded.insert(tree.scene_id.clone(), content.clone());
+			content
+		}
+	};
+	let mut children = Vec::with_capacity(tree.children.len());
+	for child in tree.children.iter() {
+		children.push(
+			Box::pin(resolve_tree(api, child, remaining, loaded)).await?,
+		);
+	}
+	Ok(ResolvedSceneTree {
+		scene_id: tree.scene_id.clone(),
+		file: tree.file.clone(),
+		content,
+		children,
+	})
+}
+
+async fn load_content(
+	api: &Services,
+	tree: &SceneIncludeTree,
+	remaining: &mut usize,
+) -> Result<SceneContent> {
+	// check the known size before loading the file
+	if let Some(integrity) = &tree.integrity {
+		if integrity.size as usize > *remaining {
+			return Err(ResolvedTooLarge.into());
+		}
+	}
+	let crate_id = tree.scene_id.crate_id();
+	let bytes = match &tree.file {
+		SceneFile::InlineJson { json } => json.as_bytes().to_vec(),
+		file => {
+			let path = file.path().unwrap_or_default();
+			api.get_crate_file(crate_id, path).await?.to_vec()
+		}
+	};
+	*remaining = remaining.checked_sub(bytes.len()).ok_or(ResolvedTooLarge)?;
+	// the file may have changed in storage since it was ingested
+	if let Some(integrity) = &tree.integrity {
+		if FileIntegrity::new(&bytes) != *integrity {
+			anyhow::bail!(
+				"{}: content does not match the integrity recorded at ingest",
+				tree.scene_id
+			);
+		}
+	}
+	let content = match &tree.file {
+		SceneFile::Bsn { .. } => SceneContent::Base64 {
+			base64: base64::engine::general_purpose::STANDARD.encode(&bytes),
+		},
+		_ => SceneContent::Text {
+			text: String::from_utf8(bytes)?,
+		},
+	};
+	Ok(content)
+}
+
+
+#[cfg(test)]
+mod test {
+	use super::resolve_tree;
+	use crate::prelude::*;
+	use crate::scene_doc::test_utils::*;
+	use anyhow::Result;
+	use std::collections::HashMap;
+	use sweet::*;
+
+	#[tokio::test]
+	async fn works() -> Result<()> {
+		let api = Services::test(FakeRegistry::new());
+		let child = inline_tree("child", vec![]);
+		let tree = inline_tree("parent", vec![child.clone(), child]);
+
+		let resolve = |tree: SceneIncludeTree, mut remaining: usize| {
+			let api = api.clone();
+			async move {
+				resolve_tree(&api, &tree, &mut remaining, &mut HashMap::new())
+					.await
+					.map(|resolved| (resolved, remaining))
+			}
+		};
+
+		let (resolved, remaining) =
+			resolve(tree.clone(), MAX_RESOLVED_BYTES).await?;
+		expect(&resolved.children[1].content).to_be(&SceneContent::Text {
+			text: r#"{"name":"child"}"#.into(),
+		})?;
+		// the second child is only counted once
+		expect(remaining).to_be(MAX_RESOLVED_BYTES - 33)?;
+
+		let err = resolve(tree.clone(), 20).await.unwrap_err();
+		expect(err.downcast_ref::<ResolvedTooLarge>()).to_be_some()?;
+
+		let mut changed = tree;
+		changed.integrity = Some(FileIntegrity::new(b"{}"));
+		let err = resolve(changed, MAX_RESOLVED_BYTES).await.unwrap_err();
+		expect(err.to_string().as_str()).to_contain("integrity")?;
+		Ok(())
+	}
+}
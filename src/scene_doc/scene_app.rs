@@ -15,11 +15,12 @@ pub struct SceneApp {
 impl SceneApp {
 	pub async fn from_manifest(
 		api: &Services,
-		cargo_lock: &CargoLock,
+		resolver: &CrateResolver,
 		manifest_crate_id: &CrateId,
 		manifest_metadata: &ManifestMetadata,
 		scene: &ManifestScene,
 		app: &ManifestApp,
+		include_path: &IncludePath,
 	) -> Result<Self> {
 		match app {
 			ManifestApp::Wasm {
@@ -41,6 +42,8 @@ impl SceneApp {
 				let (crate_name, scene_name) =
 					app.into_crate_and_scene(&manifest_crate_id.name)?;
 
+				let include_path = include_path
+					.push(&manifest_crate_id.into_scene_id(&scene.name))?;
 				if crate_name == manifest_crate_id.name {
 					let sibling_scene =
 						manifest_metadata.find_scene(&scene_name)?;
@@ -56,15 +59,16 @@ impl SceneApp {
 
 					Box::pin(Self::from_manifest(
 						api,
-						cargo_lock,
+						resolver,
 						manifest_crate_id,
 						manifest_metadata,
 						&sibling_scene,
 						sibling_app,
+						&include_path,
 					))
 					.await
 				} else {
-					let crate_id = cargo_lock.crate_id(&crate_name)?;
+					let crate_id = resolver.crate_id(api, &crate_name).await?;
 
 					let scene = api
 						.scene_doc(&SceneId::new(crate_id, &scene_name))
@@ -101,4 +105,11 @@ pub enum BevyBinary {
 	},
 }
 
-impl BevyBinary {}
+impl BevyBinary {
+	/// The scene this app belongs to
+	pub fn scene_id(&self) -> &SceneId {
+		match self {
+			Self::Wasm { scene_id, .. } => scene_id,
+		}
+	}
+}
@@ -0,0 +1,164 @@
+use crate::prelude::*;
+use anyhow::Result;
+use axum::body::Bytes;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashSet;
+use ts_rs::TS;
+
+/// Name of the lock file at the root of a bundle
+pub const BUNDLE_LOCK_FILE: &str = "bevyhub.lock.json";
+
+/// The exact scenes in a bundle, in include order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct SceneBundleLock {
+	/// The scene the bundle was created for
+	pub scene_id: SceneId,
+	pub scenes: Vec<BundledScene>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct BundledScene {
+	pub scene_id: SceneId,
+	/// Path of the scene file in the bundle,
+	/// in format `crate_name/version/path`
+	pub path: String,
+	pub integrity: Option<FileIntegrity>,
+}
+
+impl Services {
+	/// A `.tar.gz` of every file in the include tree of a scene,
+	/// with a [SceneBundleLock], cached in storage.
+	pub async fn scene_bundle(&self, scene_id: &SceneId) -> Result<Bytes> {
+		let bundle_path = storage_path::bundle_path(scene_id);
+		if let Ok(bytes) = self.storage().get(&bundle_path).await {
+			return Ok(bytes);
+		}
+		let doc = self.scene_doc(scene_id).await?;
+		let (lock, files) =
+			collect_files(self, &doc.scene_include_tree).await?;
+		let bytes = bundle_tarball(&lock, files)?;
+		self.storage().put(&bundle_path, bytes.clone()).await?;
+		Ok(bytes)
+	}
+
+	/// Remove cached bundles, ie after an included scene is re-ingested
+	pub async fn remove_scene_bundles(
+		&self,
+		scene_ids: &[SceneId],
+	) -> Result<()> {
+		for scene_id in scene_ids.iter() {
+			let bundle_path = storage_path::bundle_path(scene_id);
+			if self.storage().exists(&bundle_path).await? {
+				self.storage().delete(&bundle_path).await?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Load each scene in the tree once, in depth first order
+async fn collect_files(
+	api: &Services,
+	tree: &SceneIncludeTree,
+) -> Result<(SceneBundleLock, Vec<(String, Bytes)>)> {
+	let mut lock = SceneBundleLock {
+		scene_id: tree.scene_id.clone(),
+		scenes: Vec::new(),
+	};
+	let mut files = Vec::new();
+	let mut visited = HashSet::new();
+	let mut stack = vec![tree];
+	while let Some(node) = stack.pop() {
+		stack.extend(node.children.iter().rev());
+		if !visited.insert(&node.scene_id) {
+			continue;
+		}
+		let crate_id = node.scene_id.crate_id();
+		let (path, bytes) = match &node.file {
+			SceneFile::InlineJson { json } => (
+				format!("inline/{}.json", node.scene_id.scene_name()),
+				Bytes::from(json.clone()),
+			),
+			file => {
+				let path = file.path().unwrap_or_default();
+				(path.to_string(), api.get_crate_file(crate_id, path).await?)
+			}
+		};
+		let path = format!("{}/{}", crate_id.path(), path);
+		lock.scenes.push(BundledScene {
+			scene_id: node.scene_id.clone(),
+			path: path.clone(),
+			integrity: node.integrity.clone(),
+		});
+		files.push((path, bytes));
+	}
+	Ok((lock, files))
+}
+
+/// Create a gzipped tarball with the lock file and scene files,
+/// all inside a directory named after the scene.
+pub fn bundle_tarball(
+	lock: &SceneBundleLock,
+	files: Vec<(String, Bytes)>,
+) -> Result<Bytes> {
+	let root = lock.scene_id.scene_name();
+	let lock_file = Bytes::from(serde_json::to_vec_pretty(lock)?);
+	let mut builder =
+		tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+	for (path, bytes) in
+		std::iter::once((BUNDLE_LOCK_FILE.to_string(), lock_file)).chain(files)
+	{
+		let mut header = tar::Header::new_gnu();
+		header.set_size(bytes.len() as u64);
+		header.set_mode(0o644);
+		header.set_cksum();
+		builder.append_data(
+			&mut header,
+			format!("{root}/{path}"),
+			bytes.as_ref(),
+		)?;
+	}
+	let bytes = builder.into_inner()?.finish()?;
+	Ok(bytes.into())
+}
+
+
+#[cfg(test)]
+mod test {
+	use super::collect_files;
+	use crate::prelude::*;
+	use crate::scene_doc::test_utils::*;
+	use anyhow::Result;
+	use sweet::*;
+
+	#[tokio::test]
+	async fn works() -> Result<()> {
+		let api = Services::test(FakeRegistry::new());
+		let tree = inline_tree("parent", vec![
+			inline_tree("child", vec![]),
+			inline_tree("child", vec![]),
+		]);
+		let (lock, files) = collect_files(&api, &tree).await?;
+		expect(lock.scenes.len()).to_be(2)?;
+
+		let entries = tarball_entries(&bundle_tarball(&lock, files)?)?;
+		let paths = entries
+			.iter()
+			.map(|(path, _)| path.as_str())
+			.collect::<Vec<_>>();
+		expect(paths).to_be(vec![
+			BUNDLE_LOCK_FILE,
+			"foo/0.1.0/inline/parent.json",
+			"foo/0.1.0/inline/child.json",
+		])?;
+		let lock_file =
+			serde_json::from_slice::<SceneBundleLock>(&entries[0].1)?;
+		expect(lock_file).to_be(lock)?;
+		expect(entries[2].1.as_ref())
+			.to_be(br#"{"name":"child"}"#.as_slice())?;
+		Ok(())
+	}
+}
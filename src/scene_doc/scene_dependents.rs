@@ -0,0 +1,431 @@
+use crate::prelude::*;
+use anyhow::Result;
+use mongodb::bson::doc;
+use mongodb::bson::Document;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use ts_rs::TS;
+
+/// Maximum number of scenes returned by [Services::find_dependents]
+pub const MAX_DEPENDENTS_LIMIT: i64 = 100;
+
+/// Query parameters for `/dependents`, all fields are optional
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(default, deny_unknown_fields)]
+pub struct DependentsQuery {
+	#[ts(type = "number | null")]
+	pub limit: Option<i64>,
+	#[ts(type = "number | null")]
+	pub skip: Option<u64>,
+	/// Only scenes in the latest version of their crate
+	pub latest: bool,
+	/// Leave out the `scene_include_tree` of each scene,
+	/// the `scene_include_graph` lists the same scenes once
+	pub omit_tree: bool,
+}
+
+impl DependentsQuery {
+	/// Scenes other than `scene_id` that include it or use its app
+	pub fn to_filter(&self, scene_id: &SceneId) -> Document {
+		let doc_id = scene_id.into_doc_id().0;
+		let mut filter = doc! {
+			"_id": { "$ne": &doc_id },
+			"$or": [
+				{ "includes": { "$in": [&doc_id] } },
+				{
+					"app.binary.scene_id.crate_id.name": scene_id.crate_name(),
+					"app.binary.scene_id.crate_id.version":
+						scene_id.version().to_string(),
+					"app.binary.scene_id.scene_name": scene_id.scene_name(),
+				},
+			],
+		};
+		if self.latest {
+			filter.insert("is_latest", true);
+		}
+		filter
+	}
+}
+
+impl Services {
+	/// Scenes that include the scene or use its app,
+	/// at most [MAX_DEPENDENTS_LIMIT]
+	pub async fn find_dependents(
+		&self,
+		scene_id: &SceneId,
+		query: &DependentsQuery,
+	) -> Result<Vec<SceneDoc>> {
+		let scenes = self.db().scenes();
+		let mut builder = scenes
+			.find()
+			.filter(query.to_filter(scene_id))
+			.sort(doc! {
+				"scene_id.crate_id.name": 1,
+				"scene_id.scene_name": 1,
+				"created_ms": -1,
+			})
+			.limit_at_most(query.limit, MAX_DEPENDENTS_LIMIT);
+		if let Some(skip) = query.skip {
+			builder = builder.skip(skip);
+		}
+		builder.send().await?.try_collect().await
+	}
+
+	/// Scenes that include the scene, directly or through other scenes
+	pub async fn scene_dependents(
+		&self,
+		scene_id: &SceneId,
+	) -> Result<Vec<SceneDoc>> {
+		self.db()
+			.scenes()
+			.find()
+			.filter(doc! { "includes": { "$in": [scene_id.into_doc_id().0] } })
+			.send()
+			.await?
+			.try_collect()
+			.await
+	}
+
+	/// Queue updates of scenes in other crates that include or use the app
+	/// of any of the re-ingested `scenes`, replacing their copies of its
+	/// include tree and app. Trees that would then fail the `include_path`
+	/// checks are left as is, with the error added to the dependent.
+	/// Returns the updated dependents.
+	pub async fn queue_dependents(
+		&self,
+		scenes: &[SceneDoc],
+		include_path: &IncludePath,
+		work: &mut UnitOfWork,
+	) -> Result<Vec<SceneDoc>> {
+		// a dependent may include several of the scenes
+		let mut dependents = BTreeMap::<String, SceneDoc>::new();
+		for scene in scenes.iter() {
+			let found = self
+				.db()
+				.scenes()
+				.find()
+				.filter(
+					DependentsQuery::default().to_filter(&scene.scene_id),
+				)
+				.send()
+				.await?
+				.try_collect()
+				.await?;
+			for dependent in found {
+				if dependent.scene_id.crate_id == scene.scene_id.crate_id {
+					continue;
+				}
+				let dependent =
+					dependents.entry(dependent.doc_id().0).or_insert(dependent);
+				let mut tree = dependent.scene_include_tree.clone();
+				if tree.replace_subtree(&scene.scene_include_tree) {
+					match include_path.check_tree(&tree) {
+						Ok(()) => dependent.set_include_tree(tree),
+						Err(err) => {
+							let err = format!(
+								"failed to update included scene {}: {err}",
+								scene.scene_id
+							);
+							if !dependent.errors.contains(&err) {
+								dependent.errors.push(err);
+							}
+						}
+					}
+				}
+				if dependent
+					.app
+					.as_ref()
+					.is_some_and(|app| app.binary.scene_id() == &scene.scene_id)
+				{
+					dependent.app = scene.app.clone();
+				}
+			}
+		}
+		let dependents = dependents.into_values().collect::<Vec<_>>();
+		work.insert_many(&*self.db().scenes(), &dependents)?;
+		Ok(dependents)
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use crate::scene_doc::test_utils::*;
+	use anyhow::Result;
+	use sweet::*;
+
+	#[tokio::test]
+	async fn works() -> Result<()> {
+		let api = Services::test(FakeRegistry::new());
+		let a_manifest = |path: &str| {
+			format!(
+				r#"
+				[package]
+				name = "a"
+				version = "0.1.0"
+
+				[[package.metadata.scene]]
+				name = "base"
+				path = "{path}"
+				"#
+			)
+		};
+		let a_docs = scene_docs(
+			&api,
+			&a_manifest("scenes/old.json"),
+			&IncludePath::default(),
+		)
+		.await?;
+		api.db().scenes().insert_many(&a_docs).await?;
+		let b_docs = scene_docs(
+			&api,
+			r#"
+			[package]
+			name = "b"
+			version = "0.1.0"
+
+			[[package.metadata.scene]]
+			name = "other"
+			include = ["a/base"]
+
+			[[package.metadata.scene]]
+			name = "wrapper"
+			include = ["other"]
+			"#,
+			&IncludePath::default(),
+		)
+		.await?;
+		api.db().scenes().insert_many(&b_docs).await?;
+
+		let base = &a_docs[0].scene_id;
+		expect(&b_docs[1].includes).to_be(&vec![
+			base.into_doc_id(),
+			b_docs[0].scene_id.into_doc_id(),
+		])?;
+		expect(&b_docs[1].resolved_crates)
+			.to_be(&vec![base.crate_id.clone()])?;
+		expect(api.scene_dependents(base).await?.len()).to_be(2)?;
+
+		// re-ingest with a corrected path
+		let a_docs = scene_docs(
+			&api,
+			&a_manifest("scenes/new.json"),
+			&IncludePath::default(),
+		)
+		.await?;
+		let mut work = UnitOfWork::new();
+		let dependents = api
+			.queue_dependents(&a_docs, &IncludePath::default(), &mut work)
+			.await?;
+		api.db().commit(work).await?;
+		expect(dependents.len()).to_be(2)?;
+
+		let wrapper = api.scene_doc(&b_docs[1].scene_id).await?;
+		expect(&wrapper.scene_include_tree.children[0].children[0].file)
+			.to_be(&SceneFile::Json {
+				path: "scenes/new.json".into(),
+			})?;
+		expect(&wrapper.scene_include_graph.node(base).unwrap().file).to_be(
+			&SceneFile::Json {
+				path: "scenes/new.json".into(),
+			},
+		)?;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn updates_app_users() -> Result<()> {
+		let api = Services::test(FakeRegistry::new());
+		let a_manifest = |js_url: &str| {
+			format!(
+				r#"
+				[package]
+				name = "a"
+				version = "0.1.0"
+
+				[[package.metadata.scene]]
+				name = "base"
+				app = {{ js-url = "{js_url}", wasm-url = "main_bg.wasm" }}
+				"#
+			)
+		};
+		let a_docs =
+			scene_docs(&api, &a_manifest("old.js"), &IncludePath::default())
+				.await?;
+		api.db().scenes().insert_many(&a_docs).await?;
+		let b_docs = scene_docs(
+			&api,
+			r#"
+			[package]
+			name = "b"
+			version = "0.1.0"
+
+			[[package.metadata.scene]]
+			name = "player"
+			app = "a/base"
+			"#,
+			&IncludePath::default(),
+		)
+		.await?;
+		api.db().scenes().insert_many(&b_docs).await?;
+
+		let a_docs =
+			scene_docs(&api, &a_manifest("new.js"), &IncludePath::default())
+				.await?;
+		let mut work = UnitOfWork::new();
+		let dependents = api
+			.queue_dependents(&a_docs, &IncludePath::default(), &mut work)
+			.await?;
+		api.db().commit(work).await?;
+		expect(dependents.len()).to_be(1)?;
+
+		let player = api.scene_doc(&b_docs[0].scene_id).await?;
+		expect(&player.app).to_be(&a_docs[0].app)?;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn finds_dependents() -> Result<()> {
+		let api = Services::test(FakeRegistry::new());
+		let a_docs = scene_docs(
+			&api,
+			r#"
+			[package]
+			name = "a"
+			version = "0.1.0"
+
+			[[package.metadata.scene]]
+			name = "base"
+			app = { js-url = "main.js", wasm-url = "main_bg.wasm" }
+			"#,
+			&IncludePath::default(),
+		)
+		.await?;
+		api.db().scenes().insert_many(&a_docs).await?;
+		let mut b_docs = scene_docs(
+			&api,
+			r#"
+			[package]
+			name = "b"
+			version = "0.1.0"
+
+			[[package.metadata.scene]]
+			name = "player"
+			app = "a/base"
+
+			[[package.metadata.scene]]
+			name = "level"
+			include = ["a/base"]
+
+			[[package.metadata.scene]]
+			name = "unrelated"
+			"#,
+			&IncludePath::default(),
+		)
+		.await?;
+		b_docs[0].is_latest = true;
+		api.db().scenes().insert_many(&b_docs).await?;
+
+		let base = &a_docs[0].scene_id;
+		let find = |query: DependentsQuery| {
+			let api = api.clone();
+			async move {
+				api.find_dependents(base, &query).await.map(|scenes| {
+					scenes
+						.into_iter()
+						.map(|scene| scene.scene_id.scene_name)
+						.collect::<Vec<_>>()
+				})
+			}
+		};
+		expect(find(DependentsQuery::default()).await?)
+			.to_be(vec!["level".to_string(), "player".into()])?;
+		expect(
+			find(DependentsQuery {
+				skip: Some(1),
+				limit: Some(1),
+				..Default::default()
+			})
+			.await?,
+		)
+		.to_be(vec!["player".to_string()])?;
+		expect(
+			find(DependentsQuery {
+				latest: true,
+				..Default::default()
+			})
+			.await?,
+		)
+		.to_be(vec!["player".to_string()])?;
+		Ok(())
+	}
+
+	#[tokio::test]
+	async fn checks_updated_trees() -> Result<()> {
+		let api = Services::test(FakeRegistry::new());
+		let a_docs = scene_docs(
+			&api,
+			r#"
+			[package]
+			name = "a"
+			version = "0.1.0"
+
+			[[package.metadata.scene]]
+			name = "base"
+			"#,
+			&IncludePath::default(),
+		)
+		.await?;
+		api.db().scenes().insert_many(&a_docs).await?;
+		let b_docs = scene_docs(
+			&api,
+			r#"
+			[package]
+			name = "b"
+			version = "0.1.0"
+
+			[[package.metadata.scene]]
+			name = "level"
+			include = ["a/base"]
+			"#,
+			&IncludePath::default(),
+		)
+		.await?;
+		api.db().scenes().insert_many(&b_docs).await?;
+
+		// re-ingest with an include that makes the dependent too deep
+		let a_docs = scene_docs(
+			&api,
+			r#"
+			[package]
+			name = "a"
+			version = "0.1.0"
+
+			[[package.metadata.scene]]
+			name = "base"
+			include = ["inner"]
+
+			[[package.metadata.scene]]
+			name = "inner"
+			"#,
+			&IncludePath::new(2),
+		)
+		.await?;
+		let mut work = UnitOfWork::new();
+		let dependents = api
+			.queue_dependents(&a_docs, &IncludePath::new(2), &mut work)
+			.await?;
+		api.db().commit(work).await?;
+		expect(dependents.len()).to_be(1)?;
+
+		let level = api.scene_doc(&b_docs[0].scene_id).await?;
+		expect(&level.scene_include_tree)
+			.to_be(&b_docs[0].scene_include_tree)?;
+		expect(level.errors.len()).to_be(b_docs[0].errors.len() + 1)?;
+		expect(level.errors.last().unwrap().as_str())
+			.to_contain("exceed max depth of 2")?;
+		Ok(())
+	}
+}
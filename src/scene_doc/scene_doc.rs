@@ -1,5 +1,9 @@
 use crate::prelude::*;
 use anyhow::Result;
+use mongodb::bson::from_bson;
+use mongodb::bson::to_bson;
+use mongodb::bson::Bson;
+use mongodb::bson::Document;
 use rand::prelude::*;
 use serde::Deserialize;
 use serde::Serialize;
@@ -22,37 +26,194 @@ pub struct SceneDoc {
 	pub thumbnail: SceneThumb,
 	/// Tree of scenes to include
 	pub scene_include_tree: SceneIncludeTree,
+	/// The include tree with shared scenes listed once
+	pub scene_include_graph: SceneIncludeGraph,
+	/// Every other scene in the include tree, so dependents can be found
+	pub includes: Vec<DocId>,
 	/// Optional app binary
 	pub app: Option<SceneApp>,
 	/// Optional link to a repository
 	pub repository: Option<String>,
+	/// Keywords of the crate
+	pub keywords: Vec<String>,
 	/// Specifies whether this scene is in the latest version of the crate, defaults to false
 	pub is_latest: bool,
 	pub replication_config: ReplicationConfig,
+	/// How the versions of included crates were chosen
+	pub resolution: ResolutionStrategy,
+	/// The exact versions of other crates that the include tree
+	/// and app were resolved to, in load order
+	pub resolved_crates: Vec<CrateId>,
+	/// Summary of the scene file,
+	/// `None` for bsn scenes or if the file could not be parsed
+	pub metadata: Option<SceneMetadata>,
+	/// Problems found at ingest, ie scene files missing from the crate
+	pub errors: Vec<String>,
+	/// The [SchemaVersion] this document was written with
+	#[serde(default)]
+	pub schema_version: u32,
 }
 
 impl HasDocId for SceneDoc {
 	fn doc_id(&self) -> DocId { self._id.clone() }
+	fn from_stored(doc: Document) -> Result<Self> { upgrade_document(doc) }
+}
+
+impl NamedCollection for SceneDoc {
+	const COLLECTION_NAME: &'static str = "scenes";
+	fn indexes() -> Vec<IndexDeclaration> {
+		vec![
+			IndexDeclaration::ascending(&[
+				"scene_id.crate_id.name",
+				"scene_id.crate_id.version",
+			]),
+			IndexDeclaration::ascending(&[
+				"is_latest",
+				"scene_id.crate_id.name",
+			]),
+			IndexDeclaration::text(&["scene_id.scene_name", "description"]),
+			IndexDeclaration::ascending(&["includes"]),
+			IndexDeclaration::ascending(&["metadata.components"]),
+		]
+	}
+}
+
+impl SchemaVersion for SceneDoc {
+	const SCHEMA_VERSION: u32 = 9;
+	fn schema_version(&self) -> u32 { self.schema_version }
+	fn upgrades() -> Vec<SchemaUpgrade> {
+		vec![
+			// 0 -> 1: `replication_config` was added
+			SchemaUpgrade::new(0, |doc| {
+				if !doc.contains_key("replication_config") {
+					doc.insert(
+						"replication_config",
+						to_bson(&ReplicationConfig::default())?,
+					);
+				}
+				Ok(())
+			}),
+			// 1 -> 2: `keywords` was added, filled in by reindexing
+			SchemaUpgrade::new(1, |doc| {
+				if !doc.contains_key("keywords") {
+					doc.insert("keywords", Vec::<String>::new());
+				}
+				Ok(())
+			}),
+			// 2 -> 3: `errors` was added
+			SchemaUpgrade::new(2, |doc| {
+				if !doc.contains_key("errors") {
+					doc.insert("errors", Vec::<String>::new());
+				}
+				Ok(())
+			}),
+			// 3 -> 4: `scene_include_graph` was added
+			SchemaUpgrade::new(3, |doc| {
+				let tree = from_bson::<SceneIncludeTree>(
+					doc.get("scene_include_tree").cloned().unwrap_or_default(),
+				)?;
+				doc.insert(
+					"scene_include_graph",
+					to_bson(&SceneIncludeGraph::from_tree(&tree))?,
+				);
+				Ok(())
+			}),
+			// 4 -> 5: `resolution` was added, previously only lockfiles were used
+			SchemaUpgrade::new(4, |doc| {
+				if !doc.contains_key("resolution") {
+					doc.insert(
+						"resolution",
+						to_bson(&ResolutionStrategy::CargoLock)?,
+					);
+				}
+				Ok(())
+			}),
+			// 5 -> 6: `includes` was added
+			SchemaUpgrade::new(5, |doc| {
+				let tree = from_bson::<SceneIncludeTree>(
+					doc.get("scene_include_tree").cloned().unwrap_or_default(),
+				)?;
+				let scene_id = from_bson::<SceneId>(
+					doc.get("scene_id").cloned().unwrap_or_default(),
+				)?;
+				let graph = SceneIncludeGraph::from_tree(&tree);
+				doc.insert("includes", to_bson(&graph.includes_of(&scene_id))?);
+				Ok(())
+			}),
+			// 6 -> 7: `metadata` was added, filled in by reindexing
+			SchemaUpgrade::new(6, |doc| {
+				if !doc.contains_key("metadata") {
+					doc.insert("metadata", Bson::Null);
+				}
+				Ok(())
+			}),
+			// 7 -> 8: graph nodes are keyed by scene id
+			SchemaUpgrade::new(7, |doc| {
+				let tree = from_bson::<SceneIncludeTree>(
+					doc.get("scene_include_tree").cloned().unwrap_or_default(),
+				)?;
+				doc.insert(
+					"scene_include_graph",
+					to_bson(&SceneIncludeGraph::from_tree(&tree))?,
+				);
+				Ok(())
+			}),
+			// 8 -> 9: `resolved_crates` was added
+			SchemaUpgrade::new(8, |doc| {
+				let graph = from_bson::<SceneIncludeGraph>(
+					doc.get("scene_include_graph").cloned().unwrap_or_default(),
+				)?;
+				let scene_id = from_bson::<SceneId>(
+					doc.get("scene_id").cloned().unwrap_or_default(),
+				)?;
+				let app = from_bson::<Option<SceneApp>>(
+					doc.get("app").cloned().unwrap_or_default(),
+				)?;
+				doc.insert(
+					"resolved_crates",
+					to_bson(&resolved_crates(&scene_id, &graph, app.as_ref()))?,
+				);
+				Ok(())
+			}),
+		]
+	}
 }
 
 impl SceneDoc {
 	pub async fn from_manifest(
 		api: &Services,
 		crate_doc: &CrateDoc,
-		cargo_lock: &CargoLock,
+		resolver: &CrateResolver,
 		crate_id: &CrateId,
 		scenes: &ManifestMetadata,
 		scene: &ManifestScene,
+		include_path: &IncludePath,
 	) -> Result<Self> {
-		let tree = SceneIncludeTree::from_manifest(
-			api, cargo_lock, crate_id, scenes, scene,
+		// fetched once for the integrity and the metadata
+		let content = SceneFile::from_manifest(scene)?
+			.load(api, crate_id)
+			.await?;
+		let tree = SceneIncludeTree::from_content(
+			api,
+			resolver,
+			crate_id,
+			scenes,
+			scene,
+			content.as_deref(),
+			include_path,
 		)
 		.await?;
 
 		let app = if let Some(app) = scene.app.as_ref() {
 			Some(
 				SceneApp::from_manifest(
-					api, cargo_lock, crate_id, scenes, scene, app,
+					api,
+					resolver,
+					crate_id,
+					scenes,
+					scene,
+					app,
+					include_path,
 				)
 				.await?,
 			)
@@ -61,8 +222,23 @@ impl SceneDoc {
 		};
 
 		let scene_id = crate_id.into_scene_id(&scene.name);
+		let mut errors = tree
+			.missing_files(crate_id)
+			.into_iter()
+			.map(|path| format!("scene file not found in crate: {path}"))
+			.collect::<Vec<_>>();
+		let metadata = match SceneMetadata::from_content(
+			&tree.file,
+			content.as_deref(),
+		) {
+			Ok(metadata) => metadata,
+			Err(err) => {
+				errors.push(format!("failed to parse scene file: {err}"));
+				None
+			}
+		};
 
-		Ok(Self {
+		let mut doc = Self {
 			_id: scene_id.into_doc_id(),
 			scene_id,
 			thumbnail: SceneThumb::from_manifest(&scene),
@@ -73,18 +249,66 @@ impl SceneDoc {
 					.unwrap_or_else(|| format!("The {} scene", scene.name))
 			}),
 			created_ms: epoch_millis(),
+			scene_include_graph: SceneIncludeGraph::from_tree(&tree),
+			includes: Vec::new(),
 			scene_include_tree: tree,
 			app,
 			repository: crate_doc.repository.clone(),
+			keywords: crate_doc.keywords.clone(),
 			is_latest: false,
 			replication_config: ReplicationConfig::from_manifest(
 				&scene.replication,
 			),
-		})
+			resolution: resolver.strategy(),
+			resolved_crates: Vec::new(),
+			metadata,
+			errors,
+			schema_version: Self::SCHEMA_VERSION,
+		};
+		doc.includes = doc.scene_include_graph.includes_of(&doc.scene_id);
+		doc.resolved_crates = resolved_crates(
+			&doc.scene_id,
+			&doc.scene_include_graph,
+			doc.app.as_ref(),
+		);
+		Ok(doc)
+	}
+
+	/// Set the include tree and the fields derived from it
+	pub fn set_include_tree(&mut self, tree: SceneIncludeTree) {
+		self.scene_include_graph = SceneIncludeGraph::from_tree(&tree);
+		self.includes = self.scene_include_graph.includes_of(&self.scene_id);
+		self.resolved_crates = resolved_crates(
+			&self.scene_id,
+			&self.scene_include_graph,
+			self.app.as_ref(),
+		);
+		self.scene_include_tree = tree;
+	}
+}
+
+/// Crates other than that of `scene_id` with scenes in the `graph`
+/// or the `app`, in load order
+pub fn resolved_crates(
+	scene_id: &SceneId,
+	graph: &SceneIncludeGraph,
+	app: Option<&SceneApp>,
+) -> Vec<CrateId> {
+	let mut crates = Vec::new();
+	let scene_ids = graph
+		.load_order
+		.iter()
+		.chain(app.map(|app| app.binary.scene_id()));
+	for id in scene_ids {
+		if id.crate_id != scene_id.crate_id && !crates.contains(&id.crate_id) {
+			crates.push(id.crate_id.clone());
+		}
 	}
+	crates
 }
 
-fn epoch_millis() -> u64 {
+/// Milliseconds since the unix epoch
+pub fn epoch_millis() -> u64 {
 	std::time::SystemTime::now()
 		.duration_since(std::time::UNIX_EPOCH)
 		.unwrap()
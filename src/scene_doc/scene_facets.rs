@@ -0,0 +1,152 @@
+use crate::prelude::*;
+use anyhow::Result;
+use mongodb::bson::Bson;
+use serde::Deserialize;
+use serde::Serialize;
+use ts_rs::TS;
+
+/// Maximum number of values in each facet
+pub const MAX_FACET_VALUES: i64 = 100;
+
+/// Counts for browsing scenes, see [Services::scene_facets]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct SceneFacets {
+	/// Number of matching scenes
+	#[ts(type = "number")]
+	pub total: u64,
+	/// Scenes per crate, crates with the most scenes first
+	pub crates: Vec<FacetCount>,
+	/// Scenes per keyword, most common first
+	pub keywords: Vec<FacetCount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct FacetCount {
+	pub value: String,
+	#[ts(type = "number")]
+	pub count: u64,
+}
+
+impl From<GroupCount> for FacetCount {
+	fn from(group: GroupCount) -> Self {
+		let value = match group.value {
+			Bson::String(value) => value,
+			value => value.to_string(),
+		};
+		Self {
+			value,
+			count: group.count,
+		}
+	}
+}
+
+impl Services {
+	/// Facet counts for scenes matching the filter,
+	/// with at most `limit` values in each facet.
+	pub async fn scene_facets(
+		&self,
+		filter: &SceneFilter,
+		limit: i64,
+	) -> Result<SceneFacets> {
+		let limit = Some(limit.clamp(1, MAX_FACET_VALUES));
+		let filter = filter.to_document(self).await?;
+		let scenes = self.db().scenes();
+		let (total, crates, keywords) = futures::try_join!(
+			scenes.count(filter.clone()),
+			scenes.group_count(filter.clone(), "scene_id.crate_id.name", limit),
+			scenes.group_count(filter.clone(), "keywords", limit),
+		)?;
+		Ok(SceneFacets {
+			total,
+			crates: crates.into_iter().map(Into::into).collect(),
+			keywords: keywords.into_iter().map(Into::into).collect(),
+		})
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use crate::scene_doc::test_utils::*;
+	use anyhow::Result;
+	use mongodb::bson::Bson;
+	use sweet::*;
+
+	fn count(value: &str, count: u64) -> FacetCount {
+		FacetCount {
+			value: value.into(),
+			count,
+		}
+	}
+
+	#[tokio::test]
+	async fn works() -> Result<()> {
+		let api = Services::test(FakeRegistry::new());
+		for manifest in [
+			r#"
+			[package]
+			name = "a"
+			version = "0.1.0"
+			keywords = ["bevy", "scene"]
+
+			[[package.metadata.scene]]
+			name = "first"
+			path = "scenes/first.json"
+
+			[[package.metadata.scene]]
+			name = "second"
+			path = "scenes/second.json"
+			"#,
+			r#"
+			[package]
+			name = "b"
+			version = "0.1.0"
+			keywords = ["bevy"]
+
+			[[package.metadata.scene]]
+			name = "other"
+			path = "scenes/other.json"
+			"#,
+		] {
+			let docs =
+				scene_docs(&api, manifest, &IncludePath::default()).await?;
+			api.db().scenes().insert_many(&docs).await?;
+		}
+
+		let facets = api.scene_facets(&SceneFilter::default(), 10).await?;
+		expect(facets.total).to_be(3)?;
+		expect(facets.crates).to_be(vec![count("a", 2), count("b", 1)])?;
+		expect(facets.keywords)
+			.to_be(vec![count("bevy", 3), count("scene", 2)])?;
+
+		let filter = SceneFilter {
+			crate_name: Some("b".into()),
+			..Default::default()
+		};
+		let facets = api.scene_facets(&filter, 10).await?;
+		expect(facets.total).to_be(1)?;
+		expect(facets.crates).to_be(vec![count("b", 1)])?;
+
+		// limits are clamped to at least one and at most MAX_FACET_VALUES
+		let facets = api.scene_facets(&SceneFilter::default(), 0).await?;
+		expect(facets.crates).to_be(vec![count("a", 2)])?;
+		let scenes = api.db().scenes().find().send().await?.try_collect().await?;
+		let mut scene = scenes[0].clone();
+		scene.keywords = (0..=MAX_FACET_VALUES)
+			.map(|index| format!("keyword-{index}"))
+			.collect();
+		api.db().scenes().insert(&scene).await?;
+		let facets =
+			api.scene_facets(&SceneFilter::default(), i64::MAX).await?;
+		expect(facets.keywords.len()).to_be(MAX_FACET_VALUES as usize)?;
+
+		// non string values are formatted
+		let group = GroupCount {
+			value: Bson::Int64(3),
+			count: 1,
+		};
+		expect(FacetCount::from(group)).to_be(count("3", 1))?;
+		Ok(())
+	}
+}
@@ -0,0 +1,283 @@
+use crate::prelude::*;
+use anyhow::Result;
+use mongodb::bson::doc;
+use mongodb::bson::Document;
+use semver::VersionReq;
+use serde::Deserialize;
+use serde::Serialize;
+use ts_rs::TS;
+
+/// Maximum number of keywords in a [SceneFilter]
+pub const MAX_FILTER_KEYWORDS: usize = 10;
+/// Maximum length of [SceneFilter::text]
+pub const MAX_FILTER_TEXT_LEN: usize = 100;
+
+/// A whitelisted query for `/scenes`, compiled to a filter
+/// supported by both mongodb and [MemoryCollection].
+/// All fields are optional and combined with `and`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, TS)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneFilter {
+	pub crate_name: Option<String>,
+	/// A semver requirement like `^0.1`, requires `crate_name`
+	pub version_req: Option<String>,
+	pub is_latest: Option<bool>,
+	pub has_app: Option<bool>,
+	/// Scenes with all of these keywords
+	pub keywords: Vec<String>,
+	/// Epoch timestamp, inclusive
+	#[ts(type = "number | null")]
+	pub created_after: Option<u64>,
+	/// Epoch timestamp, exclusive
+	#[ts(type = "number | null")]
+	pub created_before: Option<u64>,
+	pub repository: Option<String>,
+	/// Full text search of the scene name and description
+	pub text: Option<String>,
+	/// Scenes with an entity that has this component,
+	/// by type path ie `bevy_core::name::Name`
+	pub component: Option<String>,
+}
+
+/// A [SceneFilter] that failed validation, as opposed to a db error
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidSceneFilter(pub String);
+
+impl std::fmt::Display for InvalidSceneFilter {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "invalid scene filter: {}", self.0)
+	}
+}
+
+impl std::error::Error for InvalidSceneFilter {}
+
+fn invalid(message: impl ToString) -> anyhow::Error {
+	InvalidSceneFilter(message.to_string()).into()
+}
+
+impl SceneFilter {
+	/// Compile to a filter document. A `version_req` is resolved to the
+	/// matching versions of the crate in the db.
+	/// # Errors
+	/// [InvalidSceneFilter] if the filter is invalid, or a db error.
+	pub async fn to_document(&self, api: &Services) -> Result<Document> {
+		if self.keywords.len() > MAX_FILTER_KEYWORDS {
+			return Err(invalid(format!(
+				"at most {} keywords allowed",
+				MAX_FILTER_KEYWORDS
+			)));
+		}
+		let mut filter = Document::new();
+		if let Some(crate_name) = &self.crate_name {
+			filter.insert("scene_id.crate_id.name", crate_name);
+		}
+		if let Some(version_req) = &self.version_req {
+			let Some(crate_name) = &self.crate_name else {
+				return Err(invalid("version_req requires crate_name"));
+			};
+			let versions = matching_versions(api, crate_name, version_req)
+				.await?
+				.into_iter()
+				.map(|version| version.to_string())
+				.collect::<Vec<_>>();
+			filter
+				.insert("scene_id.crate_id.version", doc! { "$in": versions });
+		}
+		if let Some(is_latest) = self.is_latest {
+			filter.insert("is_latest", is_latest);
+		}
+		if let Some(has_app) = self.has_app {
+			let op = if has_app { "$ne" } else { "$eq" };
+			filter.insert("app", doc! { op: null });
+		}
+		if !self.keywords.is_empty() {
+			filter.insert("keywords", doc! { "$all": &self.keywords });
+		}
+		let mut created = Document::new();
+		if let Some(after) = self.created_after {
+			created.insert("$gte", after as i64);
+		}
+		if let Some(before) = self.created_before {
+			created.insert("$lt", before as i64);
+		}
+		if !created.is_empty() {
+			filter.insert("created_ms", created);
+		}
+		if let Some(repository) = &self.repository {
+			filter.insert("repository", repository);
+		}
+		if let Some(component) = &self.component {
+			filter.insert("metadata.components", doc! { "$in": [component] });
+		}
+		if let Some(text) = &self.text {
+			if text.len() > MAX_FILTER_TEXT_LEN {
+				return Err(invalid(format!(
+					"text must be at most {} characters",
+					MAX_FILTER_TEXT_LEN
+				)));
+			}
+			filter.insert("$text", doc! { "$search": text });
+		}
+		Ok(filter)
+	}
+}
+
+/// Versions of the crate in the db that match the requirement
+async fn matching_versions(
+	api: &Services,
+	crate_name: &str,
+	version_req: &str,
+) -> Result<Vec<semver::Version>> {
+	let req = VersionReq::parse(version_req).map_err(invalid)?;
+	let crates = api
+		.db()
+		.crates()
+		.find()
+		.filter(doc! { "crate_id.name": crate_name })
+		.send()
+		.await?
+		.try_collect()
+		.await?;
+	Ok(crates
+		.into_iter()
+		.map(|doc| doc.crate_id.version)
+		.filter(|version| req.matches(version))
+		.collect())
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use mongodb::bson::doc;
+	use mongodb::bson::Document;
+	use sweet::*;
+
+	fn scene(name: &str, version: &str) -> Document {
+		doc! {
+			"_id": format!("{name}/{version}"),
+			"scene_id": {
+				"crate_id": { "name": name, "version": version },
+				"scene_name": "my-scene"
+			},
+			"description": "A beautiful scene",
+			"created_ms": 1000i64,
+			"app": null,
+			"repository": "https://github.com/foo/bar",
+			"keywords": ["bevy", "scene"],
+			"is_latest": version == "0.2.0",
+			"metadata": {
+				"entity_count": 1i64,
+				"components": [format!("{name}::Player")],
+				"resources": [],
+			},
+		}
+	}
+
+	async fn count(api: &Services, filter: SceneFilter) -> Result<usize> {
+		let collection =
+			MemoryCollection::temp().with_indexes(&SceneDoc::indexes());
+		collection
+			.insert_many(&vec![
+				scene("foo", "0.1.0"),
+				scene("foo", "0.2.0"),
+				scene("bar", "0.1.0"),
+			])
+			.await?;
+		let filter = filter.to_document(api).await?;
+		Ok(collection.count(filter).await? as usize)
+	}
+
+	#[tokio::test]
+	async fn works() -> Result<()> {
+		let api = Services::test(FakeRegistry::new());
+		for version in ["0.1.0", "0.2.0"] {
+			let crate_doc: CrateDoc = mongodb::bson::from_document(doc! {
+				"_id": format!("crates.io/foo/{version}"),
+				"crate_id": { "name": "foo", "version": version },
+				"readme": "",
+				"repository": null,
+				"description": null,
+				"keywords": [],
+				"authors": [],
+				"categories": [],
+				"created_ms": 0i64,
+				"is_latest": false,
+			})?;
+			api.db().crates().insert(&crate_doc).await?;
+		}
+
+		let filter = |f: fn(&mut SceneFilter)| {
+			let mut filter = SceneFilter::default();
+			f(&mut filter);
+			filter
+		};
+
+		expect(count(&api, SceneFilter::default()).await?).to_be(3)?;
+		expect(
+			count(&api, filter(|f| f.crate_name = Some("foo".into()))).await?,
+		)
+		.to_be(2)?;
+		expect(
+			count(
+				&api,
+				filter(|f| {
+					f.crate_name = Some("foo".into());
+					f.version_req = Some("^0.2".into());
+				}),
+			)
+			.await?,
+		)
+		.to_be(1)?;
+		expect(count(&api, filter(|f| f.is_latest = Some(true))).await?)
+			.to_be(1)?;
+		expect(count(&api, filter(|f| f.has_app = Some(true))).await?)
+			.to_be(0)?;
+		expect(
+			count(&api, filter(|f| f.keywords = vec!["bevy".into()])).await?,
+		)
+		.to_be(3)?;
+		expect(
+			count(&api, filter(|f| f.keywords = vec!["other".into()])).await?,
+		)
+		.to_be(0)?;
+		expect(count(&api, filter(|f| f.created_after = Some(1000))).await?)
+			.to_be(3)?;
+		expect(count(&api, filter(|f| f.created_before = Some(1000))).await?)
+			.to_be(0)?;
+		expect(
+			count(
+				&api,
+				filter(|f| {
+					f.repository = Some("https://github.com/foo/bar".into())
+				}),
+			)
+			.await?,
+		)
+		.to_be(3)?;
+		expect(
+			count(&api, filter(|f| f.text = Some("BEAUTIFUL".into()))).await?,
+		)
+		.to_be(3)?;
+		// only the text index fields are searched
+		expect(count(&api, filter(|f| f.text = Some("github".into()))).await?)
+			.to_be(0)?;
+		expect(
+			count(&api, filter(|f| f.component = Some("foo::Player".into())))
+				.await?,
+		)
+		.to_be(2)?;
+
+		// version_req without crate_name
+		let err = filter(|f| f.version_req = Some("^0.2".into()))
+			.to_document(&api)
+			.await
+			.unwrap_err();
+		expect(err.downcast_ref::<InvalidSceneFilter>()).to_be_some()?;
+		// unknown fields are rejected
+		expect(serde_json::from_str::<SceneFilter>(r#"{"$where":"1"}"#))
+			.to_be_err()?;
+		Ok(())
+	}
+}
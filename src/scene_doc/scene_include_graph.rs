@@ -0,0 +1,125 @@
+use crate::prelude::*;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use ts_rs::TS;
+
+/// A [SceneIncludeTree] with each scene listed once,
+/// so shared includes are not repeated.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct SceneIncludeGraph {
+	/// Each scene in the tree, keyed by the [SceneId] as a string,
+	/// ie `{crate_name}/{scene_name}/{version}`
+	pub nodes: BTreeMap<String, SceneIncludeNode>,
+	/// Each include, from the including scene to the included one
+	pub edges: Vec<SceneIncludeEdge>,
+	/// Scenes ordered so that each is loaded after the scenes it includes,
+	/// the root scene is last.
+	pub load_order: Vec<SceneId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct SceneIncludeNode {
+	pub scene_id: SceneId,
+	pub file: SceneFile,
+	pub integrity: Option<FileIntegrity>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct SceneIncludeEdge {
+	pub from: SceneId,
+	pub to: SceneId,
+}
+
+impl SceneIncludeGraph {
+	pub fn from_tree(tree: &SceneIncludeTree) -> Self {
+		let mut graph = Self::default();
+		graph.visit(tree, &mut HashSet::new());
+		graph
+	}
+
+	pub fn node(&self, scene_id: &SceneId) -> Option<&SceneIncludeNode> {
+		self.nodes.get(&scene_id.to_string())
+	}
+
+	/// Scenes directly included by a scene
+	pub fn includes<'a>(
+		&'a self,
+		scene_id: &'a SceneId,
+	) -> impl Iterator<Item = &'a SceneId> {
+		self.edges
+			.iter()
+			.filter(move |edge| &edge.from == scene_id)
+			.map(|edge| &edge.to)
+	}
+
+	/// Doc ids of every scene in the graph other than `scene_id`
+	pub fn includes_of(&self, scene_id: &SceneId) -> Vec<DocId> {
+		self.load_order
+			.iter()
+			.filter(|id| *id != scene_id)
+			.map(|id| id.into_doc_id())
+			.collect()
+	}
+
+	/// Depth first, adding each node after its children.
+	/// Trees are acyclic, see [IncludePath].
+	fn visit<'a>(
+		&mut self,
+		tree: &'a SceneIncludeTree,
+		visited: &mut HashSet<&'a SceneId>,
+	) {
+		if !visited.insert(&tree.scene_id) {
+			return;
+		}
+		for child in tree.children.iter() {
+			self.visit(child, visited);
+			let edge = SceneIncludeEdge {
+				from: tree.scene_id.clone(),
+				to: child.scene_id.clone(),
+			};
+			if !self.edges.contains(&edge) {
+				self.edges.push(edge);
+			}
+		}
+		self.nodes.insert(tree.scene_id.to_string(), SceneIncludeNode {
+			scene_id: tree.scene_id.clone(),
+			file: tree.file.clone(),
+			integrity: tree.integrity.clone(),
+		});
+		self.load_order.push(tree.scene_id.clone());
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use crate::scene_doc::test_utils::*;
+	use anyhow::Result;
+	use sweet::*;
+
+	#[test]
+	fn works() -> Result<()> {
+		// diamond, both children include hello-world
+		let root = inline_tree("root", vec![
+			inline_tree("left", vec![inline_tree("hello-world", vec![])]),
+			inline_tree("right", vec![inline_tree("hello-world", vec![])]),
+		]);
+		let graph = SceneIncludeGraph::from_tree(&root);
+		let names = graph
+			.load_order
+			.iter()
+			.map(|scene_id| scene_id.scene_name())
+			.collect::<Vec<_>>();
+		expect(names).to_be(vec!["hello-world", "left", "right", "root"])?;
+		expect(graph.nodes.len()).to_be(4)?;
+		expect(graph.nodes.contains_key("foo/hello-world/0.1.0")).to_be_true()?;
+		expect(graph.edges.len()).to_be(4)?;
+		expect(graph.includes(&root.scene_id).count()).to_be(2)?;
+		expect(graph.node(&root.children[1].children[0].scene_id))
+			.to_be_some()?;
+		Ok(())
+	}
+}
@@ -1,5 +1,6 @@
 use crate::prelude::*;
 use anyhow::Result;
+use axum::body::Bytes;
 use serde::Deserialize;
 use serde::Serialize;
 use ts_rs::TS;
@@ -10,6 +11,8 @@ use ts_rs::TS;
 pub struct SceneIncludeTree {
 	/// The path of the unpkged scene
 	pub file: SceneFile,
+	/// Size and hash of the file, `None` if it is missing from the crate
+	pub integrity: Option<FileIntegrity>,
 	pub scene_id: SceneId,
 	pub children: Vec<SceneIncludeTree>,
 }
@@ -33,6 +36,36 @@ pub enum SceneFile {
 }
 
 impl SceneFile {
+	/// Path relative to the crate root, `None` for inline scenes
+	pub fn path(&self) -> Option<&str> {
+		match self {
+			Self::Json { path } | Self::Ron { path } | Self::Bsn { path } => {
+				Some(path)
+			}
+			Self::InlineJson { .. } => None,
+		}
+	}
+
+	/// Contents of the file in the unpacked crate,
+	/// or `None` if the file is missing.
+	pub async fn load(
+		&self,
+		api: &Services,
+		crate_id: &CrateId,
+	) -> Result<Option<Bytes>> {
+		let Some(path) = self.path() else {
+			if let Self::InlineJson { json } = self {
+				return Ok(Some(Bytes::from(json.clone())));
+			}
+			return Ok(None);
+		};
+		let path = storage_path::unpkg_path(crate_id, path);
+		if !api.storage().exists(&path).await? {
+			return Ok(None);
+		}
+		api.storage().get(&path).await.map(Some)
+	}
+
 	pub fn from_manifest(manifest: &ManifestScene) -> Result<Self> {
 		if let Some(scene_json) = &manifest.scene_json {
 			Ok(Self::InlineJson {
@@ -66,49 +99,115 @@ impl SceneIncludeTree {
 	pub fn new(
 		scene_id: SceneId,
 		scene: SceneFile,
+		integrity: Option<FileIntegrity>,
 		children: Vec<SceneIncludeTree>,
 	) -> Self {
 		Self {
 			scene_id,
 			file: scene,
+			integrity,
 			children,
 		}
 	}
 
+	/// Paths of files of this crate that are missing, including those of children
+	pub fn missing_files(&self, crate_id: &CrateId) -> Vec<String> {
+		let mut missing = Vec::new();
+		if self.integrity.is_none() && &self.scene_id.crate_id == crate_id {
+			if let Some(path) = self.file.path() {
+				missing.push(path.to_string());
+			}
+		}
+		for child in self.children.iter() {
+			for path in child.missing_files(crate_id) {
+				if !missing.contains(&path) {
+					missing.push(path);
+				}
+			}
+		}
+		missing
+	}
+
+	/// Replace each copy of the tree of a scene in the children,
+	/// returns true if any were found.
+	pub fn replace_subtree(&mut self, tree: &SceneIncludeTree) -> bool {
+		let mut replaced = false;
+		for child in self.children.iter_mut() {
+			if child.scene_id == tree.scene_id {
+				*child = tree.clone();
+				replaced = true;
+			} else {
+				replaced |= child.replace_subtree(tree);
+			}
+		}
+		replaced
+	}
+
 	pub async fn from_manifest(
 		api: &Services,
-		cargo_lock: &CargoLock,
+		resolver: &CrateResolver,
 		manifest_crate_id: &CrateId,
 		manifest_metadata: &ManifestMetadata,
 		manifest_scene: &ManifestScene,
+		include_path: &IncludePath,
 	) -> Result<Self> {
+		let content = SceneFile::from_manifest(manifest_scene)?
+			.load(api, manifest_crate_id)
+			.await?;
+		Self::from_content(
+			api,
+			resolver,
+			manifest_crate_id,
+			manifest_metadata,
+			manifest_scene,
+			content.as_deref(),
+			include_path,
+		)
+		.await
+	}
+
+	/// Build the tree with the already loaded `content` of the scene file
+	pub async fn from_content(
+		api: &Services,
+		resolver: &CrateResolver,
+		manifest_crate_id: &CrateId,
+		manifest_metadata: &ManifestMetadata,
+		manifest_scene: &ManifestScene,
+		content: Option<&[u8]>,
+		include_path: &IncludePath,
+	) -> Result<Self> {
+		let id = manifest_crate_id.into_scene_id(&manifest_scene.name);
+		let include_path = include_path.push(&id)?;
 		let scene_file = SceneFile::from_manifest(manifest_scene)?;
+		let integrity = content.map(FileIntegrity::new);
 		let dependencies = Self::build_dependencies(
 			api,
-			cargo_lock,
+			resolver,
 			manifest_crate_id,
 			manifest_metadata,
 			&manifest_scene.get_includes(),
+			&include_path,
 		)
 		.await?;
-		let id = manifest_crate_id.into_scene_id(&manifest_scene.name);
-		Ok(Self::new(id, scene_file, dependencies))
+		Ok(Self::new(id, scene_file, integrity, dependencies))
 	}
 
 	pub async fn build_dependencies(
 		api: &Services,
-		cargo_lock: &CargoLock,
+		resolver: &CrateResolver,
 		manifest_crate_id: &CrateId,
 		manifest_metadata: &ManifestMetadata,
 		deps: &Vec<ManifestDependency>,
+		include_path: &IncludePath,
 	) -> Result<Vec<Self>> {
 		let futs = deps.iter().map(|dep| {
 			Self::build_dependency(
 				api,
-				cargo_lock,
+				resolver,
 				manifest_crate_id,
 				manifest_metadata,
 				dep,
+				include_path,
 			)
 		});
 		futures::future::try_join_all(futs).await
@@ -118,10 +217,11 @@ impl SceneIncludeTree {
 
 	async fn build_dependency(
 		api: &Services,
-		cargo_lock: &CargoLock,
+		resolver: &CrateResolver,
 		manifest_crate_id: &CrateId,
 		manifest_metadata: &ManifestMetadata,
 		dep: &ManifestDependency,
+		include_path: &IncludePath,
 	) -> Result<Self> {
 		let (crate_name, scene_name) =
 			dep.into_crate_and_scene(&manifest_crate_id.name)?;
@@ -130,17 +230,18 @@ impl SceneIncludeTree {
 
 			return Self::from_manifest(
 				api,
-				cargo_lock,
+				resolver,
 				manifest_crate_id,
 				manifest_metadata,
 				sibling_scene,
+				include_path,
 			)
 			.await;
 		} else {
-			let external_crate_id = cargo_lock.crate_id(&crate_name)?;
+			let external_crate_id = resolver.crate_id(api, &crate_name).await?;
 			let scene_id = SceneId::new(external_crate_id, &scene_name);
 			let scene_doc = api.scene_doc(&scene_id).await?;
-
+			include_path.check_tree(&scene_doc.scene_include_tree)?;
 			Ok(scene_doc.scene_include_tree)
 		}
 	}
@@ -150,7 +251,9 @@ impl SceneIncludeTree {
 #[cfg(test)]
 mod test {
 	use crate::prelude::*;
+	use crate::scene_doc::test_utils::*;
 	use anyhow::Result;
+	use semver::Version;
 	use sweet::*;
 
 	#[tokio::test]
@@ -173,4 +276,47 @@ mod test {
 
 		Ok(())
 	}
+
+	#[tokio::test]
+	async fn missing_files() -> Result<()> {
+		let api = Services::test(FakeRegistry::new());
+		let crate_id = CrateId::new("missing-files", Version::new(0, 1, 0));
+		api.storage()
+			.put(
+				&storage_path::unpkg_path(&crate_id, "scenes/present.json"),
+				r#"{"entities":{}}"#.into(),
+			)
+			.await?;
+		let docs = scene_docs(
+			&api,
+			r#"
+			[package]
+			name = "missing-files"
+			version = "0.1.0"
+
+			[[package.metadata.scene]]
+			name = "gone"
+			path = "scenes/gone.ron"
+
+			[[package.metadata.scene]]
+			name = "present"
+			path = "scenes/present.json"
+			include = ["gone"]
+			"#,
+			&IncludePath::default(),
+		)
+		.await?;
+		expect(&docs[0].errors).to_be(&vec![
+			"scene file not found in crate: scenes/gone.ron".to_string(),
+		])?;
+		expect(docs[0].scene_include_tree.integrity.is_none()).to_be_true()?;
+		// a present file with a missing include
+		expect(&docs[1].errors).to_be(&vec![
+			"scene file not found in crate: scenes/gone.ron".to_string(),
+		])?;
+		expect(docs[1].scene_include_tree.integrity.is_some()).to_be_true()?;
+		expect(docs[1].metadata.as_ref().map(|meta| meta.entity_count))
+			.to_be(Some(0))?;
+		Ok(())
+	}
 }
@@ -0,0 +1,140 @@
+use crate::prelude::*;
+use anyhow::Result;
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use ts_rs::TS;
+
+/// A summary of a `.json` or `.ron` bevy scene file, parsed at ingest
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, TS)]
+pub struct SceneMetadata {
+	#[ts(type = "number")]
+	pub entity_count: u64,
+	/// Type paths of components used by any entity, sorted
+	pub components: Vec<String>,
+	/// Type paths of resources, sorted
+	pub resources: Vec<String>,
+}
+
+/// The parts of a serialized `DynamicScene` we summarize,
+/// component and resource values are skipped.
+#[derive(Deserialize)]
+struct RawScene {
+	#[serde(default)]
+	resources: BTreeMap<String, IgnoredAny>,
+	/// Entity ids are numbers in ron and strings in json
+	#[serde(default)]
+	entities: BTreeMap<u64, RawEntity>,
+}
+
+#[derive(Deserialize)]
+struct RawEntity {
+	#[serde(default)]
+	components: BTreeMap<String, IgnoredAny>,
+}
+
+impl From<RawScene> for SceneMetadata {
+	fn from(scene: RawScene) -> Self {
+		let components = scene
+			.entities
+			.values()
+			.flat_map(|entity| entity.components.keys().cloned())
+			.collect::<BTreeSet<_>>();
+		Self {
+			entity_count: scene.entities.len() as u64,
+			components: components.into_iter().collect(),
+			resources: scene.resources.into_keys().collect(),
+		}
+	}
+}
+
+impl SceneMetadata {
+	pub fn from_json(json: &str) -> Result<Self> {
+		Ok(serde_json::from_str::<RawScene>(json)?.into())
+	}
+
+	pub fn from_ron(ron: &str) -> Result<Self> {
+		Ok(ron::from_str::<RawScene>(ron)?.into())
+	}
+
+	/// Parse the `content` of a scene file, `None` for bsn scenes
+	/// which are not yet supported, or missing files.
+	pub fn from_content(
+		file: &SceneFile,
+		content: Option<&[u8]>,
+	) -> Result<Option<Self>> {
+		let parse: fn(&str) -> Result<Self> = match file {
+			SceneFile::Json { .. } | SceneFile::InlineJson { .. } => {
+				Self::from_json
+			}
+			SceneFile::Ron { .. } => Self::from_ron,
+			SceneFile::Bsn { .. } => return Ok(None),
+		};
+		let Some(content) = content else {
+			return Ok(None);
+		};
+		parse(std::str::from_utf8(content)?).map(Some)
+	}
+}
+
+
+#[cfg(test)]
+mod test {
+	use crate::prelude::*;
+	use anyhow::Result;
+	use sweet::*;
+
+	#[test]
+	fn works() -> Result<()> {
+		let ron = SceneMetadata::from_ron(
+			r#"(
+			resources: {
+				"my_game::Score": (value: 1),
+			},
+			entities: {
+				4294967296: (
+					components: {
+						"bevy_transform::components::transform::Transform": (
+							translation: (x: 0.0, y: 1.0, z: 0.0),
+						),
+						"bevy_core::name::Name": "player",
+					},
+				),
+				4294967297: (
+					components: {
+						"bevy_render::view::visibility::Visibility": Inherited,
+						"bevy_core::name::Name": "camera",
+					},
+				),
+			},
+		)"#,
+		)?;
+		expect(&ron).to_be(&SceneMetadata {
+			entity_count: 2,
+			components: vec![
+				"bevy_core::name::Name".into(),
+				"bevy_render::view::visibility::Visibility".into(),
+				"bevy_transform::components::transform::Transform".into(),
+			],
+			resources: vec!["my_game::Score".into()],
+		})?;
+
+		let json = SceneMetadata::from_json(
+			r#"{
+				"entities": {
+					"4294967296": {
+						"components": { "bevy_core::name::Name": "player" }
+					}
+				}
+			}"#,
+		)?;
+		expect(json.entity_count).to_be(1)?;
+		expect(json.resources.len()).to_be(0)?;
+
+		expect(SceneMetadata::from_ron("(entities: {")).to_be_err()?;
+		expect(SceneMetadata::from_json(r#"{"entities": 1}"#)).to_be_err()?;
+		Ok(())
+	}
+}
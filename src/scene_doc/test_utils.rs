@@ -0,0 +1,64 @@
+//! Helpers shared by scene tests
+use crate::prelude::*;
+use anyhow::Result;
+
+/// Lock file for the `a` and `b` crates used in tests
+const CARGO_LOCK: &str = r#"
+	version = 3
+
+	[[package]]
+	name = "a"
+	version = "0.1.0"
+
+	[[package]]
+	name = "b"
+	version = "0.1.0"
+	"#;
+
+/// A tree node in crate `foo@0.1.0` with inline json content
+pub fn inline_tree(
+	scene_name: &str,
+	children: Vec<SceneIncludeTree>,
+) -> SceneIncludeTree {
+	let json = format!(r#"{{"name":"{scene_name}"}}"#);
+	SceneIncludeTree::new(
+		SceneId::with_crate_name(
+			"foo",
+			semver::Version::new(0, 1, 0),
+			scene_name,
+		),
+		SceneFile::InlineJson { json: json.clone() },
+		Some(FileIntegrity::new(json.as_bytes())),
+		children,
+	)
+}
+
+/// Scene docs for each scene in the manifest,
+/// resolving includes of crates `a` and `b` at `0.1.0`
+pub async fn scene_docs(
+	api: &Services,
+	manifest: &str,
+	path: &IncludePath,
+) -> Result<Vec<SceneDoc>> {
+	let manifest = toml::from_str::<CargoManifest>(manifest)?;
+	let resolver =
+		CrateResolver::Lock(toml::from_str::<CargoLock>(CARGO_LOCK)?);
+	let crate_doc = CrateDoc::from_manifest(&manifest, None)?;
+	let metadata = manifest.package.unwrap().metadata.unwrap();
+	let mut docs = Vec::new();
+	for scene in metadata.scene.iter() {
+		docs.push(
+			SceneDoc::from_manifest(
+				api,
+				&crate_doc,
+				&resolver,
+				&crate_doc.crate_id,
+				&metadata,
+				scene,
+				path,
+			)
+			.await?,
+		);
+	}
+	Ok(docs)
+}
@@ -1,16 +1,25 @@
 use crate::prelude::*;
 use axum::body::Bytes;
 use axum::extract::Path;
+use axum::extract::Query;
 use axum::extract::State;
+use axum::http::header;
+use axum::http::StatusCode;
 use axum::middleware;
+use axum::response::Html;
 use axum::response::Json;
 use axum::response::Response;
 use axum::routing::get;
 use axum::Router;
 use semver::Version;
+use serde::Deserialize;
 
 pub fn crate_routes() -> AppRouter {
 	Router::new()
+		.route(
+			"/crates",
+			get(find_crates).layer(middleware::from_fn(no_cache)),
+		)
 		.route(
 			"/crates/:crate_name/versions",
 			get(get_versions).layer(middleware::from_fn(no_cache)),
@@ -20,6 +29,10 @@ pub fn crate_routes() -> AppRouter {
 			get(unpkg),
 		)
 		.route("/crates/:crate_name/versions/:version", get(get_crate_doc))
+		.route(
+			"/crates/:crate_name/versions/:version/readme",
+			get(get_crate_readme),
+		)
 		.route(
 			"/crates/:crate_name/versions/:version/scenes",
 			get(get_crate_scene_doc_list),
@@ -28,9 +41,26 @@ pub fn crate_routes() -> AppRouter {
 			"/crates/:crate_name/versions/:version/scenes/:scene_name",
 			get(get_crate_scene_doc),
 		)
+		.route(
+			"/crates/:crate_name/versions/:version/scenes/:scene_name/bundle",
+			get(get_scene_bundle),
+		)
+		.route(
+			"/crates/:crate_name/versions/:version/scenes/:scene_name/dependents",
+			get(get_scene_dependents),
+		)
 }
 
 
+/// List crates, see [CrateQuery]
+async fn find_crates(
+	State(api): State<Services>,
+	Query(query): Query<CrateQuery>,
+) -> AppResult<Json<Vec<CrateDoc>>> {
+	let crates = api.find_crates(&query).await?;
+	Ok(Json(crates))
+}
+
 /// Get a specific file, like `scenes/my-scene.json` from a crate
 async fn unpkg(
 	State(api): State<Services>,
@@ -65,10 +95,26 @@ async fn get_crate_doc(
 	no_cache_if_latest(Json(doc), &version_param)
 }
 
+/// Get the crate README as sanitized html
+async fn get_crate_readme(
+	State(api): State<Services>,
+	Path((crate_name, version_param)): Path<(String, String)>,
+) -> AppResult<Response> {
+	let version = api
+		.registry()
+		.version_or_latest(&crate_name, &version_param)
+		.await?;
+	let html = api
+		.crate_readme_html(&CrateId::new(&crate_name, version))
+		.await?;
+	no_cache_if_latest(Html(html), &version_param)
+}
+
 /// Get a [SceneDoc] list for a crate
 async fn get_crate_scene_doc_list(
 	State(api): State<Services>,
 	Path((crate_name, version_param)): Path<(String, String)>,
+	Query(SceneQuery { omit_tree, .. }): Query<SceneQuery>,
 ) -> AppResult<Response> {
 	let version = api
 		.registry()
@@ -77,10 +123,11 @@ async fn get_crate_scene_doc_list(
 	let docs = api
 		.all_scene_docs(&CrateId::new(&crate_name, version))
 		.await?;
-	no_cache_if_latest(Json(docs), &version_param)
+	no_cache_if_latest(scenes_json(&docs, omit_tree)?, &version_param)
 }
 
-/// Get a [SceneDoc] for a crate
+/// Get a [SceneDoc] for a crate,
+/// or a [ResolvedScene] if `resolve=true`
 async fn get_crate_scene_doc(
 	State(api): State<Services>,
 	Path((crate_name, version_param, scene_name)): Path<(
@@ -88,12 +135,96 @@ async fn get_crate_scene_doc(
 		String,
 		String,
 	)>,
+	Query(SceneQuery { resolve, omit_tree }): Query<SceneQuery>,
 ) -> AppResult<Response> {
 	let version = api
 		.registry()
 		.version_or_latest(&crate_name, &version_param)
 		.await?;
 	let scene_id = SceneId::with_crate_name(&crate_name, version, scene_name);
-	let doc = api.scene_doc(&scene_id).await?;
-	no_cache_if_latest(Json(doc), &version_param)
+	if !resolve {
+		let doc = api.scene_doc(&scene_id).await?;
+		return no_cache_if_latest(
+			scenes_json(&doc, omit_tree)?,
+			&version_param,
+		);
+	}
+	let resolved =
+		api.resolve_scene(&scene_id).await.map_err(|err| match err
+			.downcast_ref::<ResolvedTooLarge>(
+		) {
+			Some(too_large) => {
+				AppError::new(StatusCode::PAYLOAD_TOO_LARGE, too_large)
+			}
+			None => err.into(),
+		})?;
+	no_cache_if_latest(Json(resolved), &version_param)
+}
+
+/// Get a `.tar.gz` of every file in the include tree of a scene,
+/// see [SceneBundleLock]
+async fn get_scene_bundle(
+	State(api): State<Services>,
+	Path((crate_name, version_param, scene_name)): Path<(
+		String,
+		String,
+		String,
+	)>,
+) -> AppResult<Response> {
+	let version = api
+		.registry()
+		.version_or_latest(&crate_name, &version_param)
+		.await?;
+	let scene_id = SceneId::with_crate_name(&crate_name, version, scene_name);
+	let bytes = api.scene_bundle(&scene_id).await?;
+	let filename = format!(
+		"{}-{}-{}.tar.gz",
+		scene_id.crate_name(),
+		scene_id.version(),
+		scene_id.scene_name()
+	);
+	let response = (
+		[
+			(header::CONTENT_TYPE, "application/gzip".to_string()),
+			(
+				header::CONTENT_DISPOSITION,
+				format!("attachment; filename=\"{filename}\""),
+			),
+		],
+		bytes,
+	);
+	no_cache_if_latest(response, &version_param)
+}
+
+/// Get each [SceneDoc] that includes the scene or uses its app,
+/// see [DependentsQuery]
+async fn get_scene_dependents(
+	State(api): State<Services>,
+	Path((crate_name, version_param, scene_name)): Path<(
+		String,
+		String,
+		String,
+	)>,
+	Query(query): Query<DependentsQuery>,
+) -> AppResult<Response> {
+	let version = api
+		.registry()
+		.version_or_latest(&crate_name, &version_param)
+		.await?;
+	let scene_id = SceneId::with_crate_name(&crate_name, version, scene_name);
+	let dependents = api.find_dependents(&scene_id, &query).await?;
+	no_cache_if_latest(
+		scenes_json(&dependents, query.omit_tree)?,
+		&version_param,
+	)
+}
+
+#[derive(Deserialize)]
+pub struct SceneQuery {
+	/// Embed the content of each scene in the include tree
+	#[serde(default)]
+	pub resolve: bool,
+	/// Leave out the `scene_include_tree` of each scene
+	#[serde(default)]
+	pub omit_tree: bool,
 }
@@ -0,0 +1,21 @@
+use crate::prelude::*;
+use axum::extract::State;
+use axum::middleware;
+use axum::response::Json;
+use axum::routing::get;
+use axum::Router;
+
+pub fn ingest_routes() -> AppRouter {
+	Router::new().route(
+		"/ingest/status",
+		get(get_ingest_status).layer(middleware::from_fn(no_cache)),
+	)
+}
+
+/// Get the [IngestStatus] of the ingestion queue
+async fn get_ingest_status(
+	State(api): State<Services>,
+) -> AppResult<Json<IngestStatus>> {
+	let status = api.ingest_status().await?;
+	Ok(Json(status))
+}
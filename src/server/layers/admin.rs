@@ -0,0 +1,18 @@
+use axum::http::HeaderMap;
+use subtle::ConstantTimeEq;
+
+/// Header checked against the `ADMIN_TOKEN` env var
+pub const ADMIN_TOKEN_HEADER: &str = "x-admin-token";
+
+/// Whether the request has a valid admin token,
+/// always false if `ADMIN_TOKEN` is not set.
+/// The token is compared in constant time.
+pub fn is_admin(headers: &HeaderMap) -> bool {
+	let Ok(token) = std::env::var("ADMIN_TOKEN") else {
+		return false;
+	};
+	let Some(value) = headers.get(ADMIN_TOKEN_HEADER) else {
+		return false;
+	};
+	!token.is_empty() && bool::from(value.as_bytes().ct_eq(token.as_bytes()))
+}
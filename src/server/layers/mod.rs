@@ -1,3 +1,6 @@
+pub mod admin;
+#[allow(unused_imports)]
+pub use self::admin::*;
 pub mod cors;
 #[allow(unused_imports)]
 pub use self::cors::*;
@@ -7,6 +7,9 @@ pub use self::app_state::*;
 pub mod crate_routes;
 #[allow(unused_imports)]
 pub use self::crate_routes::*;
+pub mod ingest_routes;
+#[allow(unused_imports)]
+pub use self::ingest_routes::*;
 pub mod layers;
 pub mod scene_routes;
 #[allow(unused_imports)]
@@ -14,3 +17,6 @@ pub use self::scene_routes::*;
 pub mod server;
 #[allow(unused_imports)]
 pub use self::server::*;
+pub mod validate_routes;
+#[allow(unused_imports)]
+pub use self::validate_routes::*;
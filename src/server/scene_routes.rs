@@ -1,31 +1,52 @@
 use crate::prelude::*;
+use anyhow::Result;
 use axum::extract::Query;
 use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::http::StatusCode;
 use axum::middleware;
+use axum::response::sse::Event;
+use axum::response::sse::KeepAlive;
+use axum::response::sse::Sse;
 use axum::response::Json;
 use axum::routing::get;
 use axum::Router;
+use futures::Stream;
+use futures::StreamExt;
 use mongodb::bson::Bson;
 use serde::Deserialize;
 
 pub fn scene_routes() -> AppRouter {
-	Router::new().route(
-		"/scenes",
-		get(find_scenes).layer(middleware::from_fn(no_cache)),
-	)
+	Router::new()
+		.route(
+			"/scenes",
+			get(find_scenes).layer(middleware::from_fn(no_cache)),
+		)
+		.route(
+			"/scenes/facets",
+			get(scene_facets).layer(middleware::from_fn(no_cache)),
+		)
+		.route(
+			"/scenes/changes",
+			get(scene_changes).layer(middleware::from_fn(no_cache)),
+		)
 }
 
 /// hard limit of 100 responses
 /// Ignored for in-memory databases
 async fn find_scenes(
 	State(api): State<Services>,
+	headers: HeaderMap,
 	Query(ListQuery {
 		limit,
 		skip,
+		query,
 		filter,
+		omit_tree,
 	}): Query<ListQuery>,
-) -> AppResult<Json<Vec<SceneDoc>>> {
-	let mut builder = api.db().scenes().find();
+) -> AppResult<Json<serde_json::Value>> {
+	let scenes = api.db().scenes();
+	let mut builder = scenes.find();
 	if let Some(skip) = skip {
 		builder = builder.skip(skip);
 	}
@@ -33,7 +54,21 @@ async fn find_scenes(
 	let limit = limit.unwrap_or(100).min(100);
 	builder = builder.limit(limit);
 
+	if let Some(query) = query {
+		let doc = parse_scene_filter(&query)?
+			.to_document(&api)
+			.await
+			.map_err(filter_error)?;
+		builder = builder.filter(doc);
+	}
+
 	if let Some(filter) = filter {
+		if !is_admin(&headers) {
+			return Err(AppError::new(
+				StatusCode::FORBIDDEN,
+				"raw filters require an admin token, use `query` instead",
+			));
+		}
 		let json = serde_json::from_str::<serde_json::Value>(&filter)?;
 		let Bson::Document(doc) = mongodb::bson::to_bson(&json)? else {
 			return Err(anyhow::anyhow!(
@@ -42,17 +77,121 @@ async fn find_scenes(
 			)
 			.into());
 		};
-		tracing::info!("applying filter: {:?}", doc);
+		tracing::info!("applying raw filter: {:?}", doc);
 		builder = builder.filter(doc);
 	}
 	let scenes = builder.send().await?.try_collect().await?;
-	Ok(Json(scenes))
+	scenes_json(&scenes, omit_tree)
+}
+
+async fn scene_facets(
+	State(api): State<Services>,
+	Query(FacetsQuery { limit, query }): Query<FacetsQuery>,
+) -> AppResult<Json<SceneFacets>> {
+	let filter = match query {
+		Some(query) => parse_scene_filter(&query)?,
+		None => SceneFilter::default(),
+	};
+	let facets = api
+		.scene_facets(&filter, limit.unwrap_or(20))
+		.await
+		.map_err(filter_error)?;
+	Ok(Json(facets))
+}
+
+/// Server sent events for each inserted or updated scene,
+/// the event name is the [ChangeKind] and the data is the [ChangeEvent].
+/// Lambda buffers responses so streaming is unsupported there.
+async fn scene_changes(
+	State(api): State<Services>,
+) -> AppResult<Sse<impl Stream<Item = Result<Event>>>> {
+	if std::env::var("AWS_LAMBDA_FUNCTION_NAME").is_ok() {
+		return Err(AppError::new(
+			StatusCode::NOT_IMPLEMENTED,
+			"scene changes are not supported on lambda",
+		));
+	}
+	let changes = api.db().scenes().watch().await.map_err(|err| {
+		AppError::new(
+			StatusCode::NOT_IMPLEMENTED,
+			format!("scene changes are not supported: {err}"),
+		)
+	})?;
+	let events = changes.filter_map(|change| async move {
+		match change {
+			Ok(change) if change.kind == ChangeKind::Remove => None,
+			Ok(change) => Some(
+				Event::default()
+					.event(change.kind.as_str())
+					.json_data(&change)
+					.map_err(anyhow::Error::from),
+			),
+			Err(err) => Some(Err(err)),
+		}
+	});
+	Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+fn parse_scene_filter(query: &str) -> AppResult<SceneFilter> {
+	serde_json::from_str::<SceneFilter>(query).map_err(bad_request)
+}
+
+fn bad_request(err: impl ToString) -> AppError {
+	AppError::new(StatusCode::BAD_REQUEST, err)
+}
+
+/// Invalid filters are a bad request, other errors are from the db
+fn filter_error(err: anyhow::Error) -> AppError {
+	match err.downcast_ref::<InvalidSceneFilter>() {
+		Some(invalid) => bad_request(invalid),
+		None => err.into(),
+	}
+}
+
+#[derive(Deserialize)]
+pub struct FacetsQuery {
+	/// Maximum values per facet
+	pub limit: Option<i64>,
+	/// A json encoded [SceneFilter]
+	#[serde(default)]
+	pub query: Option<String>,
 }
 
 #[derive(Deserialize)]
 pub struct ListQuery {
 	pub limit: Option<i64>,
 	pub skip: Option<u64>,
+	/// A json encoded [SceneFilter]
+	#[serde(default)]
+	pub query: Option<String>,
+	/// A raw json mongodb filter, only accepted with an admin token
 	#[serde(default)]
 	pub filter: Option<String>,
+	/// Leave out the `scene_include_tree` of each scene
+	#[serde(default)]
+	pub omit_tree: bool,
+}
+
+/// Serialize a [SceneDoc] or a list of them, leaving out the
+/// `scene_include_tree` if `omit_tree`. Deep trees repeat shared includes,
+/// the `scene_include_graph` lists each scene once.
+pub fn scenes_json(
+	scenes: &impl serde::Serialize,
+	omit_tree: bool,
+) -> AppResult<Json<serde_json::Value>> {
+	let mut value = serde_json::to_value(scenes)?;
+	if omit_tree {
+		let remove_tree = |doc: &mut serde_json::Value| {
+			if let Some(doc) = doc.as_object_mut() {
+				doc.remove("scene_include_tree");
+			}
+		};
+		match &mut value {
+			serde_json::Value::Array(docs) => {
+				docs.iter_mut().for_each(remove_tree)
+			}
+			doc => remove_tree(doc),
+		}
+	}
+	Ok(Json(value))
 }
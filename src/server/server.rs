@@ -23,6 +23,8 @@ pub async fn server() -> Result<Router> {
 		.merge(app_routes())
 		.merge(scene_routes())
 		.merge(crate_routes())
+		.merge(ingest_routes())
+		.merge(validate_routes())
 		// .merge(crate_routes())
 		.with_state(state)
 		.layer(
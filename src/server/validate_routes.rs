@@ -0,0 +1,33 @@
+use crate::prelude::*;
+use axum::body::Bytes;
+use axum::extract::DefaultBodyLimit;
+use axum::http::StatusCode;
+use axum::response::Json;
+use axum::routing::post;
+use axum::Router;
+
+/// Matches the crates.io upload limit
+pub const MAX_CRATE_UPLOAD_BYTES: usize = 10 * 1024 * 1024;
+
+pub fn validate_routes() -> AppRouter {
+	Router::new().route(
+		"/validate",
+		post(validate_crate).layer(DefaultBodyLimit::max(MAX_CRATE_UPLOAD_BYTES)),
+	)
+}
+
+/// Validate an uploaded `.crate` tarball, see [ManifestReport]
+async fn validate_crate(body: Bytes) -> AppResult<Json<ManifestReport>> {
+	let report = ManifestReport::from_tarball(&body).map_err(|err| {
+		match err.downcast_ref::<TarballTooLarge>() {
+			Some(too_large) => {
+				AppError::new(StatusCode::PAYLOAD_TOO_LARGE, too_large)
+			}
+			None => AppError::new(
+				StatusCode::BAD_REQUEST,
+				format!("invalid crate tarball: {err}"),
+			),
+		}
+	})?;
+	Ok(Json(report))
+}
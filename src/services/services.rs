@@ -25,3 +25,17 @@ impl Services {
 		})
 	}
 }
+
+#[cfg(test)]
+impl Services {
+	/// Local storage and a temporary in-memory db,
+	/// with crates served by `registry`.
+	pub fn test(registry: FakeRegistry) -> Self {
+		Self {
+			storage: ObjectStorageEnum::Local(FsStorage),
+			registry: CargoRegistryEnum::Fake(registry),
+			db: DocumentDbEnum::Memory(MemoryDb::temp()),
+			env: ApiEnvironment::Local,
+		}
+	}
+}
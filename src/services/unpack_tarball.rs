@@ -8,6 +8,32 @@ use tar::Archive;
 use crate::prelude::*;
 
 
+/// Stored alongside the files of a locally packaged workspace crate,
+/// so that `workspace = true` fields can be resolved.
+pub const WORKSPACE_MANIFEST_FILE: &str = "Cargo.workspace.toml";
+
+/// Maximum total size of the files in a `.crate` tarball once decompressed
+pub const MAX_UNPACKED_BYTES: u64 = 128 * 1024 * 1024;
+/// Maximum number of entries in a `.crate` tarball
+pub const MAX_TARBALL_ENTRIES: usize = 10_000;
+
+/// Returned when a tarball exceeds [MAX_UNPACKED_BYTES]
+/// or [MAX_TARBALL_ENTRIES]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TarballTooLarge;
+
+impl std::fmt::Display for TarballTooLarge {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"crate tarball exceeds {} entries or {} bytes decompressed",
+			MAX_TARBALL_ENTRIES, MAX_UNPACKED_BYTES
+		)
+	}
+}
+
+impl std::error::Error for TarballTooLarge {}
+
 /// Functions for getting files that all crates should have
 /// The inner workings are not public 
 impl Services {
@@ -18,6 +44,25 @@ impl Services {
 		let cargo_manifest = toml_from_bytes(&bytes)?;
 		Ok(cargo_manifest)
 	}
+	/// The workspace of the crate, either declared in its `Cargo.toml` or
+	/// a [WORKSPACE_MANIFEST_FILE] stored when it was packaged locally.
+	/// Published crates are already normalized so usually have neither.
+	pub async fn workspace_manifest(
+		&self,
+		crate_id: &CrateId,
+		manifest: &CargoManifest,
+	) -> Result<Option<cargo_manifest::Workspace>> {
+		if let Some(workspace) = &manifest.workspace {
+			return Ok(Some(workspace.clone()));
+		}
+		let path = storage_path::unpkg_path(crate_id, WORKSPACE_MANIFEST_FILE);
+		if !self.storage().exists(&path).await? {
+			return Ok(None);
+		}
+		let bytes = self.storage().get(&path).await?;
+		let workspace_toml: CargoManifest = toml_from_bytes(&bytes)?;
+		Ok(workspace_toml.workspace)
+	}
 	/// Fetch and cache the `Cargo.lock`
 	pub async fn cargo_lock(&self, crate_id: &CrateId) -> Result<CargoLock> {
 		let path = storage_path::unpkg_path(crate_id, "Cargo.lock");
@@ -66,33 +111,62 @@ async fn get_or_unpack_tarball(
 /// Will error if no package found
 async fn unpack_tarball(api:&Services, crate_id: &CrateId) -> Result<()> {
 	let tarball = api.registry().tarball(crate_id).await?;
-	let mut archive = Archive::new(GzDecoder::new(Cursor::new(tarball)));
-
-	let to_store = archive.entries()?.map(|file|{
-		// Make sure there wasn't an I/O error
-		let mut file = file?;
-
-		// read file contents
-		let mut buff = Vec::new();
-		let _len = file.read_to_end(&mut buff)?;
-
-		// convert tarball path to storage path
-		let path = file.header().path()?;
-		let path = path.to_string_lossy()
-		.to_string()
-		.split("/").skip(1)
-		.collect::<Vec<&str>>().join("/");
-		let path =storage_path::unpkg_path(crate_id, &path);
-
-		Ok((path, Bytes::from(buff)))
-	}).collect::<Result<Vec<_>>>()?;
-
+	let to_store = tarball_entries(&tarball)?
+		.into_iter()
+		.map(|(path, bytes)| (storage_path::unpkg_path(crate_id, &path), bytes))
+		.collect();
 	api.storage().put_many(to_store).await?;
-	
-	// futures::future::try_join_all(futs).await?;
 	Ok(())
 }
 
+/// Each file in a `.crate` tarball, with paths relative to the crate root
+pub fn tarball_entries(tarball: &[u8]) -> Result<Vec<(String, Bytes)>> {
+	Ok(tarball_files(tarball, |_| true)?
+		.into_iter()
+		.map(|(path, bytes)| (path, bytes.unwrap_or_default()))
+		.collect())
+}
+
+/// Each path in a `.crate` tarball, with the contents of the files
+/// matching `read`. Fails with [TarballTooLarge] before decompressing
+/// past the limits.
+pub fn tarball_files(
+	tarball: &[u8],
+	read: impl Fn(&str) -> bool,
+) -> Result<Vec<(String, Option<Bytes>)>> {
+	let mut archive = Archive::new(GzDecoder::new(Cursor::new(tarball)));
+	let mut files = Vec::new();
+	let mut total_bytes = 0u64;
+	for file in archive.entries()? {
+		let mut file = file?;
+		// skipping an entry still decompresses it, so count every entry
+		total_bytes = total_bytes.saturating_add(file.header().size()?);
+		if files.len() >= MAX_TARBALL_ENTRIES
+			|| total_bytes > MAX_UNPACKED_BYTES
+		{
+			return Err(TarballTooLarge.into());
+		}
+		// strip the `{name}-{version}` directory
+		let path = file
+			.header()
+			.path()?
+			.to_string_lossy()
+			.split('/')
+			.skip(1)
+			.collect::<Vec<_>>()
+			.join("/");
+		let bytes = if read(&path) {
+			let mut buff = Vec::new();
+			file.read_to_end(&mut buff)?;
+			Some(Bytes::from(buff))
+		} else {
+			None
+		};
+		files.push((path, bytes));
+	}
+	Ok(files)
+}
+
 
 #[cfg(test)]
 mod test {